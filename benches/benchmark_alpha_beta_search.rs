@@ -1,13 +1,16 @@
 use chess_rust::ai::ai::Ai;
 use criterion::{criterion_group, criterion_main, Criterion};
 use chess_rust::chess::board::Board;
-use chess_rust::chess::color::Color;
 use chess_rust::ai::alpha_beta_ai::AlphaBetaAi;
 
 fn criterion_benchmark(c: &mut Criterion) {
     let board = Board::new();
-    let ai = &mut AlphaBetaAi::new(Color::White, 5);
-    c.bench_function("Move generation", |b| b.iter(|| ai.find_best_move(&board)));
+    let ai = &mut AlphaBetaAi::new(5);
+    //Every search starts from an empty table, otherwise only the first one would do any work
+    c.bench_function("Move generation", |b| b.iter(|| {
+        ai.clear_hash();
+        ai.find_best_move(&board)
+    }));
 }
 
 criterion_group!(benches, criterion_benchmark);
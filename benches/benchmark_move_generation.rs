@@ -4,6 +4,8 @@ use chess_rust::chess::board::Board;
 fn criterion_benchmark(c: &mut Criterion) {
     let board = Board::new();
     c.bench_function("Move generation", |b| b.iter(|| board.legal_moves()));
+    let middlegame = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+    c.bench_function("Move generation middlegame", |b| b.iter(|| middlegame.legal_moves()));
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -1,6 +1,18 @@
+use log::info;
 use crate::chess::{board::Board, chess_move::ChessMove};
+use super::{search_info::SearchInfo, stop_token::StopToken};
 
 
 pub trait Ai {
-    fn find_best_move(&mut self, board: &Board) -> ChessMove;
-}
\ No newline at end of file
+    //Searches until done and returns the best move, for callers that don't need to follow the search
+    fn find_best_move(&mut self, board: &Board) -> ChessMove {
+        let search_info = self.think(board, &StopToken::new(), &mut |_search_info| ()).expect("No legal moves, the game should be over");
+        let best_move = search_info.best_move().expect("A finished search has a best move");
+        info!("Picked the move {} with value {}", best_move, search_info.score);
+        best_move
+    }
+
+    //Searches until a limit is reached or the stop token is stopped, and calls on_progress with every finished
+    //iteration. Returns the last finished iteration, or None when there are no legal moves.
+    fn think(&mut self, board: &Board, stop: &StopToken, on_progress: &mut dyn FnMut(&SearchInfo)) -> Option<SearchInfo>;
+}
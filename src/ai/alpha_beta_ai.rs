@@ -1,157 +1,474 @@
 
 use core::panic;
-use crate::{ai::ai::Ai, chess::{tile::Tile, piece::Piece}};
-use std::{f64::{MIN, MAX}, sync::Mutex};
-use log::info;
-use crate::chess::{chess_move::ChessMove, board::Board, evaluation::evaluate, color::Color, game_result::GameResult};
+use crate::ai::ai::Ai;
+#[cfg(test)]
+use crate::chess::tile::Tile;
+#[cfg(test)]
+use super::search_options::DEFAULT_ASPIRATION_WINDOW;
+use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
+use std::thread;
+use super::{move_ordering::{is_tactical, MoveOrderer}, quiescence::quiescence, search_info::{SearchInfo, SearchStatistics}, search_limits::SearchLimits, search_options::SearchOptions, stop_token::StopToken, time_manager::TimeManager, transposition_table::{self, Bound, TranspositionTable}};
+use crate::chess::{chess_move::ChessMove, board::Board, evaluation::evaluate, color::Color, game_result::GameResult, piece::Piece};
 
+//How many nodes are searched between looks at the clock
+const NODES_BETWEEN_TIME_CHECKS: u64 = 1024;
+//Scores are whole pawns, so no score fits between alpha and alpha plus this
+const NULL_WINDOW: f64 = 0.5;
+//A mate scores this plus the depth left when it happens
+pub const MATE_SCORE: f64 = 200.0;
+//Wins and losses score about MATE_SCORE, far from any material balance
+pub const DECISIVE_SCORE: f64 = 100.0;
+const NULL_MOVE_MIN_DEPTH: i32 = 3;
+//Moves before this one in the ordering, and all moves at shallower depths, are never reduced
+const LATE_MOVE_INDEX: usize = 3;
+const LATE_MOVE_MIN_DEPTH: i32 = 3;
+pub const MAX_THREADS: usize = 256;
+
+//What the threads of one search share besides the transposition table
+#[derive(Default)]
+struct SharedSearch {
+    //Nodes searched by the helper threads, added in batches
+    helper_nodes: AtomicU64
+}
+
+//A negamax search, every score is from the point of view of the side to move in the position it belongs to.
+//Nothing but the transposition table and the move ordering is kept between searches, so the same AI
+//can play a whole game.
+//With more than one thread the search is a Lazy SMP: helper threads search the same position and fill the shared
+//transposition table, which makes the main thread's search faster. Only the main thread's result is used.
 pub struct AlphaBetaAi {
-    max_depth: i32,
-    color: Color,
-    alpha: Mutex<f64>,
-    beta: Mutex<f64>,
-    best_value: Mutex<f64>,
-    best_move: Mutex<ChessMove>
+    limits: SearchLimits,
+    options: SearchOptions,
+    nodes: u64,
+    transposition_table: Arc<TranspositionTable>,
+    threads: usize,
+    shared: Arc<SharedSearch>,
+    helper: bool,
+    //Checked while searching. The main thread gets the caller's token, the helpers one that the main thread stops
+    //when it is done, as they have no limits of their own.
+    stop: StopToken,
+    //Nodes of a helper thread already added to the shared count
+    published_nodes: u64,
+    //The node count at which the clock, the stop token and the shared node count are checked next
+    next_check: u64,
+    move_orderer: MoveOrderer,
+    statistics: SearchStatistics,
+    time_manager: TimeManager,
+    //The depth of the current iteration
+    search_depth: i32,
+    //Set when a limit is reached in the middle of an iteration, the unfinished iteration is thrown away
+    aborted: bool
 }
 
 impl AlphaBetaAi {
-    pub fn new(color: Color, max_depth: i32) -> AlphaBetaAi {
-        if max_depth <= 0 {
+    pub fn new(max_depth: i32) -> AlphaBetaAi {
+        AlphaBetaAi::with_limits(SearchLimits::depth(max_depth))
+    }
+
+    pub fn with_limits(limits: SearchLimits) -> AlphaBetaAi {
+        if limits.max_depth <= 0 {
             panic!("Need to calculate at least one move deep");
         }
-        if color == Color::Empty {
-            panic!("You can't play as an empty color");
-        }
-        AlphaBetaAi{color, max_depth, 
-            alpha: Mutex::new(f64::MIN + 500.0), beta: Mutex::new(f64::MAX - 500.0), 
-            best_value: Mutex::new(f64::MIN+ 500.0), 
-            best_move: Mutex::new(ChessMove::from("0 0 1 1".to_string()).unwrap())}
-    }
-
-    fn alpha_beta_search(&mut self, board: &Board, depth: i32, maximizing_player: bool) -> f64 {
-        if depth == 0 || board.result() != GameResult::Ongoing {
-            return self.evaluate(board) + depth as f64;
-        }
-        if maximizing_player {
-            let mut value = MIN;
-            let moves = board.legal_moves();
-            for chess_move in board.legal_moves().into_iter(){
-                let new_board = board.make_move_with_struct(chess_move);
-                let node_value = self.alpha_beta_search(&new_board, depth -1, false);
-                if self.is_larger(&value, &node_value) {
-                    value = node_value;
-                }
-                if depth == self.max_depth {
-                    let mut best_value = self.best_value.lock().unwrap();
-                    if self.is_larger(&best_value, &node_value) {
-                        let mut best_move = self.best_move.lock().unwrap();
-                        *best_value = node_value;
-                        *best_move = chess_move; 
-                    }
-                }
-
-                let beta_result = self.beta.lock();
-                if self.is_larger( &beta_result.unwrap(), &value) {
-                    break;
-                }
-                
-                let lock = self.alpha.lock();
-                let mut alpha = lock.unwrap();
-                if self.is_larger(&alpha, &value) {
-                    *alpha = value;
-                }
+        AlphaBetaAi{
+            options: SearchOptions::default(),
+            nodes: 0,
+            transposition_table: Arc::new(TranspositionTable::new(transposition_table::DEFAULT_SIZE_MB)),
+            threads: 1,
+            shared: Arc::new(SharedSearch::default()),
+            helper: false,
+            stop: StopToken::new(),
+            published_nodes: 0,
+            next_check: NODES_BETWEEN_TIME_CHECKS,
+            move_orderer: MoveOrderer::new(),
+            statistics: SearchStatistics::default(),
+            time_manager: TimeManager::new(&limits),
+            search_depth: 0,
+            aborted: false,
+            limits}
+    }
+
+    pub fn set_limits(&mut self, limits: SearchLimits) {
+        self.limits = limits;
+    }
+
+    pub fn set_options(&mut self, options: SearchOptions) {
+        self.options = options;
+    }
+
+    //The transposition table takes up about size_mb megabytes, at most MAX_SIZE_MB, changing the size empties it
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        self.transposition_table = Arc::new(TranspositionTable::new(size_mb.min(transposition_table::MAX_SIZE_MB)));
+    }
+
+    //The number of threads searching, clamped to between 1 and MAX_THREADS. The default of one thread searches
+    //deterministically, with more the result depends on how the threads happen to be scheduled.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.clamp(1, MAX_THREADS);
+    }
+
+    pub fn search(&mut self, board: &Board) -> SearchInfo {
+        self.search_with(board, &StopToken::new(), |_search_info| ()).expect("No legal moves, the game should be over")
+    }
+
+    //Searches one ply deeper every iteration until a limit is reached and returns the last completed iteration.
+    //Stopping the token ends the search at once, except during the first iteration. Every iteration is reported
+    //with the nodes and time of the whole search.
+    pub fn search_with<F: FnMut(&SearchInfo)>(&mut self, board: &Board, stop: &StopToken, on_iteration: F) -> Option<SearchInfo> {
+        if board.legal_moves().is_empty() {
+            return None;
+        }
+        self.transposition_table.new_search();
+        self.shared = Arc::new(SharedSearch::default());
+        self.stop = stop.clone();
+        let helpers_stop = StopToken::new();
+        thread::scope(|scope| {
+            for index in 1..self.threads {
+                let mut helper = self.new_helper(helpers_stop.clone());
+                //Half of the helpers skip the first iteration, so they stay a ply ahead of the others
+                scope.spawn(move || helper.deepen(board, 1 + index as i32 % 2, |_search_info| ()));
             }
-            value
+            let search_info = self.deepen(board, 1, on_iteration);
+            helpers_stop.stop();
+            search_info
+        })
+    }
+
+    fn new_helper(&self, stop: StopToken) -> AlphaBetaAi {
+        let limits = SearchLimits::default();
+        AlphaBetaAi {
+            options: self.options,
+            nodes: 0,
+            transposition_table: self.transposition_table.clone(),
+            threads: 1,
+            shared: self.shared.clone(),
+            helper: true,
+            stop,
+            published_nodes: 0,
+            next_check: NODES_BETWEEN_TIME_CHECKS,
+            move_orderer: MoveOrderer::new(),
+            statistics: SearchStatistics::default(),
+            time_manager: TimeManager::new(&limits),
+            search_depth: 0,
+            aborted: false,
+            limits
+        }
+    }
+
+    fn deepen<F: FnMut(&SearchInfo)>(&mut self, board: &Board, first_depth: i32, mut on_iteration: F) -> Option<SearchInfo> {
+        self.time_manager = TimeManager::new(&self.limits);
+        self.nodes = 0;
+        self.aborted = false;
+        self.published_nodes = 0;
+        self.next_check = NODES_BETWEEN_TIME_CHECKS;
+        self.move_orderer.new_search();
+        self.statistics = SearchStatistics::default();
+        let mut board = board.clone();
+        let mut last_iteration = None;
+        for depth in first_depth..=self.limits.max_depth {
+            self.search_depth = depth;
+            let previous_score = last_iteration.as_ref().map(|search_info: &SearchInfo| search_info.score);
+            let (best_move, score) = self.aspiration_search(&mut board, depth, previous_score, &mut on_iteration);
+            if self.aborted {
+                break;
+            }
+            let search_info = self.search_info(&board, depth, best_move, score, Bound::Exact);
+            on_iteration(&search_info);
+            last_iteration = Some(search_info);
+            if self.stop.is_stopped() || self.node_limit_reached() || !self.time_manager.can_start_iteration() {
+                break;
+            }
+        }
+        last_iteration
+    }
+
+    pub fn clear_hash(&mut self) {
+        self.transposition_table.clear();
+    }
+
+    //Counts the nodes of all threads
+    fn total_nodes(&self) -> u64 {
+        self.nodes + self.shared.helper_nodes.load(Ordering::Relaxed)
+    }
+
+    fn node_limit_reached(&self) -> bool {
+        self.limits.max_nodes.is_some_and(|max_nodes| self.total_nodes() >= max_nodes)
+    }
+
+    //The first iteration always finishes, so there is a move to play
+    fn should_abort(&mut self) -> bool {
+        if self.nodes < self.next_check {
+            return self.search_depth > 1 && self.node_limit_reached();
+        }
+        self.next_check = self.nodes + NODES_BETWEEN_TIME_CHECKS;
+        if self.helper {
+            self.shared.helper_nodes.fetch_add(self.nodes - self.published_nodes, Ordering::Relaxed);
+            self.published_nodes = self.nodes;
+        }
+        self.search_depth > 1 && (self.node_limit_reached() || self.stop.is_stopped() || self.time_manager.out_of_time())
+    }
+
+    //Searches with a window around the previous score, which cuts off more, and widens the side the score fell out of
+    //until it fits. Every failed attempt is reported with the bound it found.
+    fn aspiration_search<F: FnMut(&SearchInfo)>(&mut self, board: &mut Board, depth: i32, previous_score: Option<f64>, on_iteration: &mut F)
+        -> (ChessMove, f64) {
+        let mut window = self.options.aspiration_window.filter(|_| previous_score.is_some_and(|score| score.abs() < DECISIVE_SCORE));
+        let (mut alpha, mut beta) = match (window, previous_score) {
+            (Some(window), Some(score)) => (score - window, score + window),
+            _ => (f64::MIN, f64::MAX)
+        };
+        loop {
+            let (best_move, score) = self.search_root(board, depth, alpha, beta);
+            let bound = bound(score, alpha, beta);
+            if self.aborted || bound == Bound::Exact {
+                return (best_move, score);
+            }
+            on_iteration(&self.search_info(board, depth, best_move, score, bound));
+            //A window wider than any material balance might as well be the full window
+            window = window.map(|window| window * 2.0).filter(|window| *window < DECISIVE_SCORE);
+            if bound == Bound::Upper {
+                self.statistics.aspiration_fail_lows += 1;
+                alpha = window.map_or(f64::MIN, |window| score - window);
+            } else {
+                self.statistics.aspiration_fail_highs += 1;
+                beta = window.map_or(f64::MAX, |window| score + window);
+            }
+        }
+    }
+
+    //Like any other node, except that every move gets a score and the first of the best ones is kept
+    fn search_root(&mut self, board: &mut Board, depth: i32, mut alpha: f64, beta: f64) -> (ChessMove, f64) {
+        self.nodes += 1;
+        let mut chess_moves = board.legal_moves();
+        let hash_move = self.transposition_table.probe(board.hash()).and_then(|entry| entry.best_move);
+        self.move_orderer.order(board, &mut chess_moves, hash_move, 0);
+        let in_check = board.is_check(board.player_to_move());
+        let alpha_before = alpha;
+        let mut best = (chess_moves[0], f64::MIN);
+        for (index, chess_move) in chess_moves.into_iter().enumerate() {
+            let value = self.search_move(board, chess_move, index, depth, alpha, beta, 0, in_check);
+            if value > best.1 {
+                best = (chess_move, value);
+                alpha = alpha.max(value);
+            }
+            if value >= beta {
+                break;
+            }
+        }
+        if !self.aborted {
+            self.transposition_table.store(board.hash(), depth, score_to_table(best.1, depth), bound(best.1, alpha_before, beta), Some(best.0));
+        }
+        best
+    }
+
+    fn negamax(&mut self, board: &mut Board, depth: i32, mut alpha: f64, mut beta: f64, ply: usize, allow_null_move: bool) -> f64 {
+        self.nodes += 1;
+        if self.aborted || self.should_abort() {
+            self.aborted = true;
+            return 0.0;
+        }
+        //Going back to a position seen before can't gain anything, so it scores as a draw
+        if board.is_repetition() {
+            return 0.0;
+        }
+        if depth <= 0 {
+            return quiescence(board, alpha, beta, board.player_to_move(), self.options.delta_pruning, &mut self.nodes);
+        }
+        if board.result() != GameResult::Ongoing {
+            //Draws are worth nothing, a mate is worth more the sooner it comes
+            let value = self.evaluate(board);
+            return if value == 0.0 {0.0} else {value + value.signum() * depth as f64};
+        }
+        let entry = self.transposition_table.probe(board.hash());
+        //A deeper search of the position is at least as good as the one about to start
+        if let Some(entry) = entry.filter(|entry| entry.depth >= depth) {
+            let score = score_from_table(entry.score, depth);
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower => alpha = alpha.max(score),
+                Bound::Upper => beta = beta.min(score)
+            }
+            if alpha >= beta {
+                return score;
+            }
+        }
+        let in_check = board.is_check(board.player_to_move());
+        if allow_null_move && self.null_move_allowed(board, depth, beta, in_check) {
+            let undo_info = board.make_null_move();
+            let reduction = if depth > 6 {3} else {2};
+            let value = -self.negamax(board, depth - 1 - reduction, -beta, -beta + NULL_WINDOW, ply + 1, false);
+            board.unmake_null_move(undo_info);
+            if value >= beta && !self.aborted {
+                self.statistics.null_move_cutoffs += 1;
+                //A mate found after passing isn't a real one
+                return if value >= DECISIVE_SCORE {beta} else {value};
+            }
+        }
+        let alpha_before = alpha;
+        let mut chess_moves = board.legal_moves();
+        self.move_orderer.order(board, &mut chess_moves, entry.and_then(|entry| entry.best_move), ply);
+        let mut value = f64::MIN;
+        let mut best_move = None;
+        for (index, chess_move) in chess_moves.into_iter().enumerate() {
+            let node_value = self.search_move(board, chess_move, index, depth, alpha, beta, ply, in_check);
+            if node_value > value {
+                value = node_value;
+                best_move = Some(chess_move);
+            }
+            if value >= beta {
+                self.record_cutoff(board, chess_move, index, ply, depth);
+                break;
+            }
+            alpha = alpha.max(value);
+        }
+        if self.aborted {
+            return value;
+        }
+        self.transposition_table.store(board.hash(), depth, score_to_table(value, depth), bound(value, alpha_before, beta), best_move);
+        value
+    }
+
+    //Plays the move and returns its score for the side that played it. Moves after the first are only searched
+    //fully when the zero window or the reduced search says they could be better than alpha.
+    #[allow(clippy::too_many_arguments)]
+    fn search_move(&mut self, board: &mut Board, chess_move: ChessMove, index: usize, depth: i32, alpha: f64, beta: f64, ply: usize,
+        in_check: bool) -> f64 {
+        let tactical = is_tactical(board, &chess_move);
+        let undo_info = board.make(chess_move);
+        let gives_check = board.is_check(board.player_to_move());
+        let extension = if self.options.check_extensions && gives_check && ply < self.search_depth as usize {1} else {0};
+        let new_depth = depth - 1 + extension;
+        let value = if index == 0 {
+            -self.negamax(board, new_depth, -beta, -alpha, ply + 1, true)
         } else {
-            let mut value = MAX;
-            for chess_move in board.legal_moves(){
-                let new_board = board.make_move_with_struct(chess_move);
-                let node_value = self.alpha_beta_search(&new_board, depth -1, true);
-                if self.is_smaller(&value, &node_value) {
-                    value = node_value;
-                }
-                {
-                    let lock = self.alpha.lock();
-                    if self.is_larger(&value, &lock.unwrap()) {
-                        break;
-                    }
-                }
-                {
-                    let lock = self.beta.lock();
-                    let mut result = lock.unwrap();
-                    if self.is_larger(&result, &value) {
-                        *result = value;
-                    }
-                }
+            let reduction = if self.options.late_move_reductions && depth >= LATE_MOVE_MIN_DEPTH && index >= LATE_MOVE_INDEX
+                && !tactical && !in_check && !gives_check {1} else {0};
+            let scout_beta = if self.options.principal_variation_search {alpha + NULL_WINDOW} else {beta};
+            let mut value = -self.negamax(board, new_depth - reduction, -scout_beta, -alpha, ply + 1, true);
+            if reduction > 0 && value > alpha {
+                self.statistics.reduction_re_searches += 1;
+                value = -self.negamax(board, new_depth, -scout_beta, -alpha, ply + 1, true);
+            }
+            if scout_beta < beta && value > alpha && value < beta {
+                self.statistics.zero_window_re_searches += 1;
+                value = -self.negamax(board, new_depth, -beta, -alpha, ply + 1, true);
             }
             value
+        };
+        board.unmake(chess_move, undo_info);
+        value
+    }
+
+    //Passing is only safe when a real move is almost always better, which isn't the case in check
+    //or with nothing but pawns left, where zugzwang is common
+    fn null_move_allowed(&self, board: &Board, depth: i32, beta: f64, in_check: bool) -> bool {
+        let side = board.player_to_move();
+        let pieces = board.occupancy(side) & !board.pieces(side, Piece::Pawn) & !board.pieces(side, Piece::King);
+        self.options.null_move_pruning && depth >= NULL_MOVE_MIN_DEPTH && !in_check && pieces != 0
+            && beta.abs() < DECISIVE_SCORE && self.evaluate(board) >= beta
+    }
+
+    fn record_cutoff(&mut self, board: &Board, chess_move: ChessMove, index: usize, ply: usize, depth: i32) {
+        self.statistics.beta_cutoffs += 1;
+        if index == 0 {
+            self.statistics.first_move_cutoffs += 1;
         }
+        self.move_orderer.record_cutoff(board, chess_move, ply, depth);
     }
 
     fn evaluate(&self, board: &Board) -> f64 {
-        match self.color {
+        match board.player_to_move() {
             Color::White => evaluate(board) as f64,
             Color::Black => -evaluate(board) as f64,
             _ => panic!("Can only evaluate for players")
         }
     }
 
-    fn is_larger(&self, value_1: &f64, value_2: &f64) -> bool{
-        value_1 - value_2 < 0.00001
+    fn search_info(&self, board: &Board, depth: i32, best_move: ChessMove, score: f64, bound: Bound) -> SearchInfo {
+        SearchInfo {
+            depth,
+            score,
+            bound,
+            principal_variation: self.principal_variation(board, best_move, depth),
+            nodes: self.total_nodes(),
+            elapsed: self.time_manager.elapsed(),
+            hashfull: self.transposition_table.hashfull(),
+            statistics: self.statistics
+        }
     }
 
-    fn is_smaller(&self, worst_value: &f64, new_value: &f64) -> bool{
-        worst_value - new_value > 0.00001
+    //The best move followed by the best moves stored in the table. The line ends at the depth searched, at a move
+    //that isn't legal, which can happen when another position took the slot, or when a position repeats.
+    fn principal_variation(&self, board: &Board, best_move: ChessMove, depth: i32) -> Vec<ChessMove> {
+        let mut board = board.clone();
+        let mut principal_variation = vec!(best_move);
+        board.make(best_move);
+        while principal_variation.len() < depth as usize && !board.is_repetition() {
+            let next_move = self.transposition_table.probe(board.hash()).and_then(|entry| entry.best_move)
+                .filter(|chess_move| board.legal_moves().contains(chess_move));
+            match next_move {
+                Some(chess_move) => {
+                    board.make(chess_move);
+                    principal_variation.push(chess_move);
+                },
+                None => break
+            }
+        }
+        principal_variation
     }
 }
 
-impl Ai for AlphaBetaAi {
-    fn find_best_move(&mut self, board: &Board) -> ChessMove {
-        self.alpha_beta_search(board, self.max_depth, true);
-        let best_move = self.best_move.lock().unwrap();
-        let best_value = self.best_value.lock().unwrap();
-        info!("Picked the move {} with value {}", best_move, best_value);
-        *best_move
-
+//Mate scores include the depth left at the mate, the table keeps them relative to the depth of the entry
+//so they can be reused at other depths
+fn score_to_table(score: f64, depth: i32) -> f64 {
+    if score >= DECISIVE_SCORE {
+        score - depth as f64
+    } else if score <= -DECISIVE_SCORE {
+        score + depth as f64
+    } else {
+        score
     }
 }
 
-#[test]
-#[should_panic]
-fn create_ai_with_depth_0_panics(){
-    let _ai = AlphaBetaAi::new(Color::Black, 0);
-
+fn score_from_table(score: f64, depth: i32) -> f64 {
+    if score >= DECISIVE_SCORE {
+        score + depth as f64
+    } else if score <= -DECISIVE_SCORE {
+        score - depth as f64
+    } else {
+        score
+    }
 }
-#[test]
-#[should_panic]
-fn create_ai_with_empty_color_panics(){
-    let _ai = AlphaBetaAi::new(Color::Empty, 1);
 
+//What a score found with the window from alpha to beta says about the real value
+fn bound(value: f64, alpha: f64, beta: f64) -> Bound {
+    if value >= beta {
+        Bound::Lower
+    } else if value <= alpha {
+        Bound::Upper
+    } else {
+        Bound::Exact
+    }
 }
-#[test]
-fn maximize(){
-    let ai = AlphaBetaAi::new(Color::White, 1);
-    assert!(!ai.is_larger(&5.0, &0.0));
-    assert!(ai.is_larger(&0.0, &5.0));
-    assert!(!ai.is_larger(&0.0, &-5.0));
-    assert!(ai.is_larger(&-5.0, &0.0));
+
+impl Ai for AlphaBetaAi {
+    fn think(&mut self, board: &Board, stop: &StopToken, on_progress: &mut dyn FnMut(&SearchInfo)) -> Option<SearchInfo> {
+        self.search_with(board, stop, on_progress)
+    }
 }
+
 #[test]
-fn minimize(){
-    let ai = AlphaBetaAi::new(Color::White, 1);
-    assert!(ai.is_smaller(&5.0, &0.0));
-    assert!(!ai.is_smaller(&0.0, &5.0));
-    assert!(ai.is_smaller(&-5.0, &-10.0));
-    assert!(!ai.is_smaller(&-5.0, &0.0));
+#[should_panic]
+fn create_ai_with_depth_0_panics(){
+    let _ai = AlphaBetaAi::new(0);
 
 }
 #[test]
 fn white_win(){
     let mut board = Board::empty();
-    board.tiles[0][0] = Tile{piece: Piece::King, color: Color::Black};
-    board.tiles[2][2] = Tile{piece: Piece::King, color: Color::White};
-    board.tiles[2][1] = Tile{piece: Piece::Queen, color: Color::White};
-    let mut ai = AlphaBetaAi::new(Color::White,3);
+    board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(2, 2, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(2, 1, Tile{piece: Piece::Queen, color: Color::White});
+    let mut ai = AlphaBetaAi::new(3);
     let best_move = ai.find_best_move(&board);
     let expected_move = ChessMove::from("2 1 1 1".to_string()).unwrap();
     assert_eq!(expected_move, best_move);
@@ -159,11 +476,11 @@ fn white_win(){
 #[test]
 fn black_win(){
     let mut board = Board::empty();
-    board.tiles[1][0] = Tile{piece: Piece::King, color: Color::White};
-    board.tiles[2][2] = Tile{piece: Piece::King, color: Color::Black};
-    board.tiles[2][1] = Tile{piece: Piece::Queen, color: Color::Black};
+    board.set_tile(1, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(2, 2, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(2, 1, Tile{piece: Piece::Queen, color: Color::Black});
     board = board.make_move(1, 0, 0, 0);
-    let mut ai = AlphaBetaAi::new(Color::Black, 3);
+    let mut ai = AlphaBetaAi::new(3);
     let best_move = ai.find_best_move(&board);
     let expected_move = ChessMove::from("2 1 1 1".to_string()).unwrap();
     assert_eq!(expected_move, best_move);
@@ -171,10 +488,10 @@ fn black_win(){
 #[test]
 fn should_take_queen(){
     let mut board = Board::empty();
-    board.tiles[1][0] = Tile{piece: Piece::King, color: Color::White};
-    board.tiles[0][2] = Tile{piece: Piece::King, color: Color::Black};
-    board.tiles[2][1] = Tile{piece: Piece::Queen, color: Color::Black};
-    let mut ai = AlphaBetaAi::new(Color::White, 3);
+    board.set_tile(1, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(0, 2, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(2, 1, Tile{piece: Piece::Queen, color: Color::Black});
+    let mut ai = AlphaBetaAi::new(3);
     let best_move = ai.find_best_move(&board);
     let expected_move = ChessMove::from("1 0 2 1".to_string()).unwrap();
     assert_eq!(expected_move, best_move);
@@ -188,7 +505,7 @@ fn should_prevent_scholars_mate_depth_3(){
     board = board.make_move(3, 0, 5, 2);
     board = board.make_move(0, 5, 1, 7);
     board = board.make_move(5, 0, 2, 3);
-    let mut ai = AlphaBetaAi::new(Color::Black, 3);
+    let mut ai = AlphaBetaAi::new(3);
     let best_move = ai.find_best_move(&board);
     board = board.make_move_with_struct(best_move);
     if board.legal_move(5, 2, 5, 6) {
@@ -205,7 +522,7 @@ fn should_prevent_scholars_mate_depth_4(){
     board = board.make_move(3, 0, 5, 2);
     board = board.make_move(0, 5, 1, 7);
     board = board.make_move(5, 0, 2, 3);
-    let mut ai = AlphaBetaAi::new(Color::Black, 4);
+    let mut ai = AlphaBetaAi::new(4);
     let best_move = ai.find_best_move(&board);
     board = board.make_move_with_struct(best_move);
     if board.legal_move(5, 2, 5, 6) {
@@ -213,3 +530,240 @@ fn should_prevent_scholars_mate_depth_4(){
         assert_eq!(GameResult::Ongoing, board.result());
     }
 }
+
+#[test]
+fn should_underpromote_to_knight_fork(){
+    let mut board = Board::empty();
+    board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(6, 6, Tile{piece: Piece::Pawn, color: Color::White});
+    //Keeps the ending after the fork winnable, knight and king alone can't mate
+    board.set_tile(1, 1, Tile{piece: Piece::Pawn, color: Color::White});
+    board.set_tile(7, 5, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(4, 6, Tile{piece: Piece::Queen, color: Color::Black});
+    let mut ai = AlphaBetaAi::new(3);
+    let best_move = ai.find_best_move(&board);
+    let expected_move = ChessMove::from("6 6 6 7 n".to_string()).unwrap();
+    assert_eq!(expected_move, best_move);
+}
+
+#[test]
+fn search_reports_nodes(){
+    let board = Board::new();
+    let mut ai = AlphaBetaAi::new(2);
+    let search_info = ai.search(&board);
+    assert_eq!(2, search_info.depth);
+    assert!(search_info.nodes > 20);
+    assert!(board.legal_moves().contains(&search_info.best_move().unwrap()));
+}
+
+
+#[test]
+fn losing_side_repeats_position(){
+    let mut board = Board::from_fen("k7/8/8/8/8/8/8/3Q3K w - - 0 1").unwrap();
+    board = board.make_move(3, 0, 3, 1);
+    board = board.make_move(0, 7, 1, 7);
+    board = board.make_move(3, 1, 3, 0);
+    let mut ai = AlphaBetaAi::new(1);
+    assert_eq!(ChessMove::new((1, 7), (0, 7)), ai.find_best_move(&board));
+}
+
+#[test]
+fn losing_side_trades_into_dead_draw(){
+    let board = Board::from_fen("k2q4/8/8/8/3Q4/4B3/8/2B4K b - - 0 1").unwrap();
+    let mut ai = AlphaBetaAi::new(2);
+    //Checking first and trading on the next move draws just as well, without the draw Black would be six pawns down
+    assert_eq!(0.0, ai.search(&board).score);
+}
+
+#[test]
+fn transposition_table_is_reused_between_searches(){
+    let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+    let mut ai = AlphaBetaAi::new(4);
+    //A small table, so the entries of a short search show up in hashfull
+    ai.set_hash_size(1);
+    let first_search = ai.search(&board);
+    assert!(first_search.hashfull > 0, "{:?}", first_search);
+    let second_search = ai.search(&board);
+    assert!(second_search.nodes < first_search.nodes);
+    assert_eq!(first_search.score, second_search.score);
+    ai.clear_hash();
+    assert!(ai.search(&board).nodes > second_search.nodes);
+}
+
+#[test]
+fn does_not_take_defended_pawn_with_queen(){
+    let board = Board::from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+    let mut ai = AlphaBetaAi::new(1);
+    assert_ne!(ChessMove::new((3, 0), (3, 4)), ai.find_best_move(&board));
+}
+
+#[test]
+fn move_ordering_cuts_off_early(){
+    let mut board = Board::new();
+    board = board.make_move(4, 1, 5, 2);
+    board = board.make_move(1, 7, 0, 5);
+    board = board.make_move(3, 0, 5, 2);
+    board = board.make_move(0, 5, 1, 7);
+    board = board.make_move(5, 0, 2, 3);
+    let mut ai = AlphaBetaAi::new(4);
+    let search_info = ai.search(&board);
+    //Searching in generation order took 16624 nodes
+    assert!(search_info.nodes < 8000);
+    assert!(search_info.statistics.first_move_cutoff_rate() > 0.7, "{:?}", search_info.statistics);
+}
+
+#[test]
+fn reused_ai_searches_every_position_afresh(){
+    let mut ai = AlphaBetaAi::new(3);
+    let first_move = ai.find_best_move(&Board::from_fen("4k3/8/8/3p4/8/8/8/3QK3 w - - 0 1").unwrap());
+    assert_eq!(ChessMove::new((3, 0), (3, 4)), first_move);
+    let board = Board::from_fen("4k3/8/8/8/8/8/1q6/R3K3 w Q - 0 1").unwrap();
+    let search_info = ai.search(&board);
+    assert!(board.legal_moves().contains(&search_info.best_move().unwrap()));
+    assert_eq!(AlphaBetaAi::new(3).search(&board).score, search_info.score);
+}
+
+#[test]
+fn selective_search_needs_fewer_nodes(){
+    let board = Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap();
+    let mut ai = AlphaBetaAi::new(4);
+    let selective_search = ai.search(&board);
+    ai.set_options(SearchOptions::exact());
+    ai.clear_hash();
+    let exact_search = ai.search(&board);
+    assert!(selective_search.nodes < exact_search.nodes, "{} {}", selective_search.nodes, exact_search.nodes);
+    assert!(selective_search.statistics.zero_window_re_searches > 0);
+    assert_eq!(0, exact_search.statistics.null_move_cutoffs);
+}
+
+#[test]
+fn every_option_alone_finds_back_rank_mate(){
+    let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1").unwrap();
+    let mut all_options = vec!(SearchOptions::default(), SearchOptions::exact());
+    for option in 0..5 {
+        let mut options = SearchOptions::exact();
+        *[&mut options.delta_pruning, &mut options.principal_variation_search, &mut options.null_move_pruning,
+            &mut options.late_move_reductions, &mut options.check_extensions][option] = true;
+        all_options.push(options);
+    }
+    all_options.push(SearchOptions {aspiration_window: Some(DEFAULT_ASPIRATION_WINDOW), ..SearchOptions::exact()});
+    for options in all_options {
+        let mut ai = AlphaBetaAi::new(4);
+        ai.set_options(options);
+        assert_eq!(ChessMove::new((3, 0), (3, 7)), ai.find_best_move(&board), "{:?}", options);
+    }
+}
+
+#[test]
+fn no_null_move_with_only_pawns_left(){
+    let ai = AlphaBetaAi::new(4);
+    let board = Board::from_fen("4k3/8/8/3p4/3P4/8/8/4K3 w - - 0 1").unwrap();
+    assert!(!ai.null_move_allowed(&board, 4, -1.0, false));
+    let board = Board::from_fen("4k3/8/8/3p4/3P4/8/8/4KN2 w - - 0 1").unwrap();
+    assert!(ai.null_move_allowed(&board, 4, -1.0, false));
+    assert!(!ai.null_move_allowed(&board, 4, -1.0, true));
+    assert!(!ai.null_move_allowed(&board, 2, -1.0, false));
+}
+
+#[test]
+fn aspiration_window_is_widened_until_the_score_fits(){
+    let board = Board::from_fen("r3k2r/8/8/2n5/3P1q2/8/8/R3K2R b KQkq - 0 1").unwrap();
+    let mut ai = AlphaBetaAi::new(3);
+    let mut reports = Vec::new();
+    let search_info = ai.search_with(&board, &StopToken::new(), |search_info| reports.push((search_info.depth, search_info.bound))).unwrap();
+    let failed_searches = reports.iter().filter(|(_depth, bound)| *bound != Bound::Exact).count() as u64;
+    assert!(failed_searches > 0);
+    assert_eq!(failed_searches, search_info.statistics.aspiration_fail_lows + search_info.statistics.aspiration_fail_highs);
+    let finished_depths: Vec<i32> = reports.iter().filter(|(_depth, bound)| *bound == Bound::Exact).map(|(depth, _bound)| *depth).collect();
+    assert_eq!(vec!(1, 2, 3), finished_depths);
+}
+
+#[test]
+fn single_thread_search_is_deterministic(){
+    let board = Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap();
+    let first_search = AlphaBetaAi::new(4).search(&board);
+    let second_search = AlphaBetaAi::new(4).search(&board);
+    assert_eq!((first_search.nodes, first_search.score, first_search.principal_variation, first_search.statistics),
+        (second_search.nodes, second_search.score, second_search.principal_variation, second_search.statistics));
+}
+
+#[test]
+fn helper_threads_find_the_same_move(){
+    let mut board = Board::new();
+    board = board.make_move(4, 1, 4, 3);
+    board = board.make_move(4, 6, 4, 4);
+    board = board.make_move(3, 0, 7, 4);
+    board = board.make_move(1, 7, 2, 5);
+    board = board.make_move(5, 0, 2, 3);
+    let mut ai = AlphaBetaAi::new(4);
+    ai.set_threads(4);
+    for _ in 0..5 {
+        let search_info = ai.search(&board);
+        let board_after_move = board.make_move_with_struct(search_info.best_move().unwrap());
+        assert!(!board_after_move.legal_move(7, 4, 5, 6) || board_after_move.make_move(7, 4, 5, 6).result() == GameResult::Ongoing);
+        assert!(search_info.score > -DECISIVE_SCORE);
+    }
+}
+
+#[test]
+fn stop_token_interrupts_the_search(){
+    let board = Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap();
+    let stop = StopToken::new();
+    let thread_stop = stop.clone();
+    let search = std::thread::spawn(move || {
+        let mut ai: Box<dyn Ai> = Box::new(AlphaBetaAi::with_limits(SearchLimits::default()));
+        let mut depths = Vec::new();
+        let search_info = ai.think(&board, &thread_stop, &mut |search_info| depths.push(search_info.depth));
+        (search_info, depths)
+    });
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    let stop_time = std::time::Instant::now();
+    stop.stop();
+    let (search_info, depths) = search.join().unwrap();
+    assert!(stop_time.elapsed() < std::time::Duration::from_millis(500));
+    let search_info = search_info.unwrap();
+    assert_eq!(Some(&search_info.depth), depths.last());
+    assert!(search_info.depth < crate::ai::search_limits::MAX_DEPTH);
+}
+
+#[test]
+fn winning_side_avoids_repetition(){
+    let mut board = Board::from_fen("k7/8/8/1p1p1p2/1P1P1P2/8/8/K1B5 w - - 0 1").unwrap();
+    board = board.make_move(0, 0, 1, 0);
+    board = board.make_move(0, 7, 1, 7);
+    board = board.make_move(1, 0, 0, 0);
+    board = board.make_move(1, 7, 0, 7);
+    let search_info = AlphaBetaAi::new(6).search(&board);
+    assert_ne!(Some(ChessMove::new((0, 0), (1, 0))), search_info.best_move());
+    assert!(search_info.score > 0.0);
+}
+
+#[test]
+fn winning_side_does_not_trade_into_dead_draw(){
+    let board = Board::from_fen("8/8/4k3/3n4/8/2N5/8/2B1K3 w - - 0 1").unwrap();
+    for depth in 5..=7 {
+        let search_info = AlphaBetaAi::new(depth).search(&board);
+        assert_ne!(Some(ChessMove::new((2, 2), (3, 4))), search_info.best_move(), "depth {}", depth);
+        assert_eq!(3.0, search_info.score, "depth {}", depth);
+    }
+}
+
+#[test]
+fn mate_scores_keep_their_distance_through_the_table(){
+    //Stored with 3 plies left and probed with 1, the mate is still as far away from the root
+    assert_eq!(201.0, score_from_table(score_to_table(203.0, 3), 1));
+    assert_eq!(-201.0, score_from_table(score_to_table(-203.0, 3), 1));
+    assert_eq!(1.5, score_from_table(score_to_table(1.5, 3), 1));
+}
+
+#[test]
+fn principal_variation_is_a_legal_line(){
+    let mut board = Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap();
+    let search_info = AlphaBetaAi::new(4).search(&board);
+    assert!(search_info.principal_variation.len() > 1);
+    assert!(search_info.principal_variation.len() <= 4);
+    for chess_move in search_info.principal_variation {
+        assert!(board.legal_moves().contains(&chess_move), "{}", chess_move.to_uci());
+        board.make(chess_move);
+    }
+}
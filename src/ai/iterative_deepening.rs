@@ -0,0 +1,51 @@
+use crate::chess::board::Board;
+use super::{alpha_beta_ai::AlphaBetaAi, search_info::SearchInfo, search_limits::SearchLimits, stop_token::StopToken};
+
+//Searches for the side to move within the limits, see AlphaBetaAi::search_with
+pub fn iterative_deepening<F: FnMut(&SearchInfo)>(board: &Board, limits: &SearchLimits, stop: &StopToken, on_iteration: F) -> Option<SearchInfo> {
+    AlphaBetaAi::with_limits(limits.clone()).search_with(board, stop, on_iteration)
+}
+
+#[test]
+fn stops_at_max_depth() {
+    let board = Board::new();
+    let mut depths = Vec::new();
+    let search_info = iterative_deepening(&board, &SearchLimits::depth(3), &StopToken::new(), |info| depths.push(info.depth)).unwrap();
+    assert_eq!(vec!(1, 2, 3), depths);
+    assert_eq!(3, search_info.depth);
+}
+
+#[test]
+fn stop_flag_ends_after_first_iteration() {
+    let board = Board::new();
+    let stop = StopToken::new();
+    stop.stop();
+    let search_info = iterative_deepening(&board, &SearchLimits::depth(10), &stop, |_info| ()).unwrap();
+    assert_eq!(1, search_info.depth);
+}
+
+#[test]
+fn no_moves_no_search() {
+    let board = Board::from_fen("k7/8/1Q6/8/8/8/8/K7 b - - 0 1").unwrap();
+    assert_eq!(None, iterative_deepening(&board, &SearchLimits::depth(3), &StopToken::new(), |_info| ()));
+}
+
+#[test]
+fn node_limit_ends_search() {
+    let board = Board::new();
+    let limits = SearchLimits {max_nodes: Some(2000), ..SearchLimits::default()};
+    let search_info = iterative_deepening(&board, &limits, &StopToken::new(), |_info| ()).unwrap();
+    assert!(search_info.depth > 1 && search_info.depth < 10);
+    assert!(board.legal_moves().contains(&search_info.best_move().unwrap()));
+}
+
+#[test]
+fn move_time_ends_search() {
+    let board = Board::new();
+    let limits = SearchLimits::move_time(std::time::Duration::from_millis(200));
+    let start = std::time::Instant::now();
+    let search_info = iterative_deepening(&board, &limits, &StopToken::new(), |_info| ()).unwrap();
+    //How deep it gets depends on the speed of the machine, but it has to stop close to the time given
+    assert!(start.elapsed().as_millis() < 1000);
+    assert!(search_info.elapsed.as_millis() < 1000);
+}
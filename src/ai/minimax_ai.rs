@@ -1,8 +1,7 @@
 
 use core::panic;
-use crate::ai::ai::Ai;
-use std::f64::{MIN, MAX};
-use log::info;
+use std::time::Instant;
+use crate::ai::{ai::Ai, quiescence::quiescence, search_info::{SearchInfo, SearchStatistics}, stop_token::StopToken, transposition_table::Bound};
 use crate::chess::{chess_move::ChessMove, board::Board, evaluation::evaluate, color::Color, game_result::GameResult};
 
 pub struct MinimaxAi {
@@ -21,27 +20,45 @@ impl MinimaxAi {
         MinimaxAi{color, max_depth}
     }
 
-    fn minimax(&self, board: &Board, depth: i32, maximizing_player: bool) -> f64 {
-        if depth == 0 || board.result() != GameResult::Ongoing {
-            return self.evaluate(board) + depth as f64;
+    //Fills principal_variation with the best line from the position, it stays empty where the search ends
+    fn minimax(&self, board: &mut Board, depth: i32, maximizing_player: bool, principal_variation: &mut Vec<ChessMove>, nodes: &mut u64) -> f64 {
+        *nodes += 1;
+        principal_variation.clear();
+        //Going back to a position seen before can't gain anything, so it scores as a draw
+        if board.is_repetition() {
+            return 0.0;
         }
+        if depth == 0 {
+            //Delta pruning depends on the window of an alpha-beta search, without it the value is exact
+            return quiescence(board, f64::MIN, f64::MAX, self.color, false, nodes);
+        }
+        if board.result() != GameResult::Ongoing {
+            //Draws are worth nothing, a mate is worth more the sooner it comes
+            let value = self.evaluate(board);
+            return if value == 0.0 {0.0} else {value + value.signum() * depth as f64};
+        }
+        let mut variation = vec!();
         if maximizing_player {
-            let mut value = MIN;
+            let mut value = f64::MIN;
             for chess_move in board.legal_moves(){
-                let new_board = board.make_move_with_struct(chess_move);
-                let node_value = self.minimax(&new_board, depth -1, false);
+                let undo_info = board.make(chess_move);
+                let node_value = self.minimax(board, depth -1, false, &mut variation, nodes);
+                board.unmake(chess_move, undo_info);
                 if self.move_maximizes(value, node_value) {
                     value = node_value;
+                    set_variation(principal_variation, chess_move, &variation);
                 }
             }
             value
         } else {
-            let mut value = MAX;
+            let mut value = f64::MAX;
             for chess_move in board.legal_moves(){
-                let new_board = board.make_move_with_struct(chess_move);
-                let node_value = self.minimax(&new_board, depth -1, true);
+                let undo_info = board.make(chess_move);
+                let node_value = self.minimax(board, depth -1, true, &mut variation, nodes);
+                board.unmake(chess_move, undo_info);
                 if self.move_minimizes(value, node_value) {
                     value = node_value;
+                    set_variation(principal_variation, chess_move, &variation);
                 }
             }
             value
@@ -66,28 +83,66 @@ impl MinimaxAi {
     }
 }
 
-impl Ai for MinimaxAi {
-    fn find_best_move(&mut self, board: &Board) -> ChessMove {
+impl MinimaxAi {
+    //Of equally good moves the one searched last is picked
+    pub fn best_move_with_value(&self, board: &Board) -> (ChessMove, f64) {
+        let (principal_variation, value) = self.search_root(board, &StopToken::new(), &mut 0);
+        (principal_variation[0], value)
+    }
+
+    //Stopping the token skips the moves not searched yet, the best of the others is picked
+    fn search_root(&self, board: &Board, stop: &StopToken, nodes: &mut u64) -> (Vec<ChessMove>, f64) {
         let mut chess_moves = board.legal_moves();
-        let mut best_move = chess_moves.pop().expect("No legal moves, the game should be over");
-        let new_board = board.make_move_with_struct(best_move);
-        let mut best_value = self.minimax(&new_board, self.max_depth - 1, false);
+        let first_move = chess_moves.pop().expect("No legal moves, the game should be over");
+        let mut board = board.clone();
+        let mut variation = vec!();
+        let undo_info = board.make(first_move);
+        let mut best_value = self.minimax(&mut board, self.max_depth - 1, false, &mut variation, nodes);
+        board.unmake(first_move, undo_info);
+        let mut principal_variation = vec!();
+        set_variation(&mut principal_variation, first_move, &variation);
         for chess_move in chess_moves {
-            let new_board = board.make_move_with_struct(chess_move);
-            let move_value = self.minimax(&new_board, self.max_depth -1, false);
+            if stop.is_stopped() {
+                break;
+            }
+            let undo_info = board.make(chess_move);
+            let move_value = self.minimax(&mut board, self.max_depth -1, false, &mut variation, nodes);
+            board.unmake(chess_move, undo_info);
             if self.move_maximizes(best_value, move_value) {
                 best_value = move_value;
-                best_move = chess_move;
+                set_variation(&mut principal_variation, chess_move, &variation);
             }
         }
-        info!("Picked the move {} with value {}", best_move, best_value);
-        best_move
+        (principal_variation, best_value)
+    }
+}
+
+//The line of a node is its best move followed by the line after that move
+fn set_variation(principal_variation: &mut Vec<ChessMove>, chess_move: ChessMove, variation: &[ChessMove]) {
+    principal_variation.clear();
+    principal_variation.push(chess_move);
+    principal_variation.extend_from_slice(variation);
+}
 
+impl Ai for MinimaxAi {
+    //There is only one iteration, searched to the full depth
+    fn think(&mut self, board: &Board, stop: &StopToken, on_progress: &mut dyn FnMut(&SearchInfo)) -> Option<SearchInfo> {
+        if board.legal_moves().is_empty() {
+            return None;
+        }
+        let start_time = Instant::now();
+        let mut nodes = 0;
+        let (principal_variation, score) = self.search_root(board, stop, &mut nodes);
+        let search_info = SearchInfo {depth: self.max_depth, score, bound: Bound::Exact, principal_variation, nodes,
+            elapsed: start_time.elapsed(), hashfull: 0, statistics: SearchStatistics::default()};
+        on_progress(&search_info);
+        Some(search_info)
     }
 }
 
+#[cfg(test)]
 mod tests {
-    use crate::{chess::{tile::Tile, board::Board, piece::Piece, color::Color, chess_move::ChessMove, game_result::GameResult}, ai::{minimax_ai::MinimaxAi, ai::Ai}};
+    use crate::{chess::{tile::Tile, board::Board, piece::Piece, color::Color, chess_move::ChessMove, game_result::GameResult}, ai::{minimax_ai::MinimaxAi, ai::Ai, alpha_beta_ai::AlphaBetaAi, search_options::SearchOptions, stop_token::StopToken}};
     #[test]
     #[should_panic]
     fn create_ai_with_depth_0_panics(){
@@ -120,9 +175,9 @@ mod tests {
     #[test]
     fn white_win(){
         let mut board = Board::empty();
-        board.tiles[0][0] = Tile{piece: Piece::King, color: Color::Black};
-        board.tiles[2][2] = Tile{piece: Piece::King, color: Color::White};
-        board.tiles[2][1] = Tile{piece: Piece::Queen, color: Color::White};
+        board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::Black});
+        board.set_tile(2, 2, Tile{piece: Piece::King, color: Color::White});
+        board.set_tile(2, 1, Tile{piece: Piece::Queen, color: Color::White});
         let mut ai = MinimaxAi::new(Color::White,3);
         let best_move = ai.find_best_move(&board);
         let expected_move = ChessMove::from("2 1 1 1".to_string()).unwrap();
@@ -131,9 +186,9 @@ mod tests {
     #[test]
     fn black_win(){
         let mut board = Board::empty();
-        board.tiles[1][0] = Tile{piece: Piece::King, color: Color::White};
-        board.tiles[2][2] = Tile{piece: Piece::King, color: Color::Black};
-        board.tiles[2][1] = Tile{piece: Piece::Queen, color: Color::Black};
+        board.set_tile(1, 0, Tile{piece: Piece::King, color: Color::White});
+        board.set_tile(2, 2, Tile{piece: Piece::King, color: Color::Black});
+        board.set_tile(2, 1, Tile{piece: Piece::Queen, color: Color::Black});
         board = board.make_move(1, 0, 0, 0);
         let mut ai = MinimaxAi::new(Color::Black, 3);
         let best_move = ai.find_best_move(&board);
@@ -143,9 +198,9 @@ mod tests {
     #[test]
     fn should_take_queen(){
         let mut board = Board::empty();
-        board.tiles[1][0] = Tile{piece: Piece::King, color: Color::White};
-        board.tiles[7][7] = Tile{piece: Piece::King, color: Color::Black};
-        board.tiles[2][1] = Tile{piece: Piece::Queen, color: Color::Black};
+        board.set_tile(1, 0, Tile{piece: Piece::King, color: Color::White});
+        board.set_tile(7, 7, Tile{piece: Piece::King, color: Color::Black});
+        board.set_tile(2, 1, Tile{piece: Piece::Queen, color: Color::Black});
         let mut ai = MinimaxAi::new(Color::White, 3);
         let best_move = ai.find_best_move(&board);
         let expected_move = ChessMove::from("1 0 2 1".to_string()).unwrap();
@@ -186,4 +241,104 @@ mod tests {
         }
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn should_underpromote_to_knight_fork(){
+        let mut board = Board::empty();
+        board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::White});
+        board.set_tile(6, 6, Tile{piece: Piece::Pawn, color: Color::White});
+        //Keeps the ending after the fork winnable, knight and king alone can't mate
+        board.set_tile(1, 1, Tile{piece: Piece::Pawn, color: Color::White});
+        board.set_tile(7, 5, Tile{piece: Piece::King, color: Color::Black});
+        board.set_tile(4, 6, Tile{piece: Piece::Queen, color: Color::Black});
+        let mut ai = MinimaxAi::new(Color::White, 3);
+        let best_move = ai.find_best_move(&board);
+        let expected_move = ChessMove::from("6 6 6 7 n".to_string()).unwrap();
+        assert_eq!(best_move, expected_move);
+    }
+
+    #[test]
+    fn losing_side_repeats_position(){
+        let mut board = Board::from_fen("k7/8/8/8/8/8/8/3Q3K w - - 0 1").unwrap();
+        board = board.make_move(3, 0, 3, 1);
+        board = board.make_move(0, 7, 1, 7);
+        board = board.make_move(3, 1, 3, 0);
+        let mut ai = MinimaxAi::new(Color::Black, 1);
+        assert_eq!(ChessMove::new((1, 7), (0, 7)), ai.find_best_move(&board));
+    }
+
+    #[test]
+    fn winning_side_avoids_repetition(){
+        let mut board = Board::from_fen("k7/8/8/1p1p1p2/1P1P1P2/8/8/K1B5 w - - 0 1").unwrap();
+        board = board.make_move(0, 0, 1, 0);
+        board = board.make_move(0, 7, 1, 7);
+        board = board.make_move(1, 0, 0, 0);
+        board = board.make_move(1, 7, 0, 7);
+        let mut ai = MinimaxAi::new(Color::White, 5);
+        assert_ne!(ChessMove::new((0, 0), (1, 0)), ai.find_best_move(&board));
+    }
+
+    #[test]
+    fn losing_side_trades_into_dead_draw(){
+        let board = Board::from_fen("k2q4/8/8/8/3Q4/4B3/8/2B4K b - - 0 1").unwrap();
+        let mut ai = MinimaxAi::new(Color::Black, 2);
+        assert_eq!(ChessMove::new((3, 7), (3, 3)), ai.find_best_move(&board));
+    }
+
+    #[test]
+    fn does_not_take_defended_pawn_with_queen(){
+        let board = Board::from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let mut ai = MinimaxAi::new(Color::White, 1);
+        assert_ne!(ChessMove::new((3, 0), (3, 4)), ai.find_best_move(&board));
+    }
+
+    #[test]
+    fn alpha_beta_finds_the_minimax_value(){
+        //Minimax is slow, the busy positions only go two deep
+        for (fen, max_depth) in [("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 2), ("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4", 2),
+            ("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1", 3), ("r3k2r/8/8/2n5/3P1q2/8/8/R3K2R b KQkq - 0 1", 2), ("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1", 3)] {
+            let board = Board::from_fen(fen).unwrap();
+            let color = board.player_to_move();
+            for depth in 1..=max_depth {
+                let minimax_ai = MinimaxAi::new(color, depth);
+                let (_, value) = minimax_ai.best_move_with_value(&board);
+                let mut alpha_beta_ai = AlphaBetaAi::new(depth);
+                alpha_beta_ai.set_options(SearchOptions::exact());
+                let search_info = alpha_beta_ai.search(&board);
+                assert_eq!(value, search_info.score, "{} at depth {}", fen, depth);
+                let mut board_after_move = board.clone();
+                board_after_move.make(search_info.best_move().unwrap());
+                assert_eq!(value, minimax_ai.minimax(&mut board_after_move, depth - 1, false, &mut vec!(), &mut 0), "{} at depth {}", fen, depth);
+            }
+        }
+    }
+
+    #[test]
+    fn think_reports_the_only_iteration(){
+        let board = Board::from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let mut ai = MinimaxAi::new(Color::White, 2);
+        let mut reports = Vec::new();
+        let search_info = ai.think(&board, &StopToken::new(), &mut |search_info| reports.push(search_info.clone())).unwrap();
+        assert_eq!(vec!(search_info.clone()), reports);
+        assert_eq!(2, search_info.depth);
+        assert!(search_info.nodes > 0);
+        assert_eq!(None, ai.think(&Board::from_fen("k7/8/1Q6/8/8/8/8/K7 b - - 0 1").unwrap(), &StopToken::new(), &mut |_search_info| ()));
+    }
+
+    #[test]
+    fn principal_variation_ends_at_mate(){
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1").unwrap();
+        let search_info = MinimaxAi::new(Color::White, 3).think(&board, &StopToken::new(), &mut |_search_info| ()).unwrap();
+        assert_eq!(vec!(ChessMove::new((3, 0), (3, 7))), search_info.principal_variation);
+    }
+
+    #[test]
+    fn principal_variation_is_a_legal_line(){
+        let mut board = Board::from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let search_info = MinimaxAi::new(Color::White, 3).think(&board, &StopToken::new(), &mut |_search_info| ()).unwrap();
+        assert_eq!(3, search_info.principal_variation.len());
+        for chess_move in search_info.principal_variation {
+            assert!(board.legal_moves().contains(&chess_move), "{}", chess_move.to_uci());
+            board.make(chess_move);
+        }
+    }
+}
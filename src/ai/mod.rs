@@ -1,3 +1,13 @@
-pub(crate) mod minimax_ai;
+pub mod minimax_ai;
+#[allow(clippy::module_inception)]
 pub mod ai;
-pub mod alpha_beta_ai;
\ No newline at end of file
+pub mod alpha_beta_ai;
+pub mod search_info;
+pub mod iterative_deepening;
+pub mod transposition_table;
+pub mod search_limits;
+pub mod search_options;
+pub mod time_manager;
+pub mod quiescence;
+pub mod move_ordering;
+pub mod stop_token;
@@ -0,0 +1,127 @@
+use crate::chess::{bitboard::{color_index, square_index}, board::Board, chess_move::ChessMove, color::Color, evaluation::piece_value, piece::Piece};
+use super::search_limits::MAX_PLY;
+
+const HASH_MOVE_SCORE: i32 = i32::MAX;
+const CAPTURE_SCORE: i32 = 3_000_000;
+const KILLER_SCORES: [i32; 2] = [2_000_000, 1_900_000];
+//History scores are halved before reaching the killers
+const MAX_HISTORY_SCORE: i32 = 1_000_000;
+
+//Remembers which quiet moves caused cutoffs, the same moves are likely to refute other moves as well.
+//Killers are kept per ply, history per side and start and end square.
+pub struct MoveOrderer {
+    killers: Vec<[Option<ChessMove>; 2]>,
+    history: Box<[[[i32; 64]; 64]; 2]>
+}
+
+impl MoveOrderer {
+    pub fn new() -> MoveOrderer {
+        MoveOrderer {killers: vec!([None; 2]; MAX_PLY + 1), history: Box::new([[[0; 64]; 64]; 2])}
+    }
+
+    //Killers are specific to the position searched, the history is only weakened
+    pub fn new_search(&mut self) {
+        self.killers.iter_mut().for_each(|killers| *killers = [None; 2]);
+        self.history.iter_mut().flatten().flatten().for_each(|score| *score /= 2);
+    }
+
+    //The hash move first, then captures by MVV-LVA, then killer moves, then the other quiet moves by history
+    pub fn order(&self, board: &Board, chess_moves: &mut [ChessMove], hash_move: Option<ChessMove>, ply: usize) {
+        chess_moves.sort_by_cached_key(|chess_move| -self.score(board, chess_move, hash_move, ply));
+    }
+
+    pub fn record_cutoff(&mut self, board: &Board, chess_move: ChessMove, ply: usize, depth: i32) {
+        if is_tactical(board, &chess_move) {
+            return;
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(chess_move) {
+            killers[1] = killers[0];
+            killers[0] = Some(chess_move);
+        }
+        let (start, end) = squares(&chess_move);
+        let history = &mut self.history[color_index(board.player_to_move())];
+        history[start][end] += depth * depth;
+        if history[start][end] > MAX_HISTORY_SCORE {
+            history.iter_mut().flatten().for_each(|score| *score /= 2);
+        }
+    }
+
+    fn score(&self, board: &Board, chess_move: &ChessMove, hash_move: Option<ChessMove>, ply: usize) -> i32 {
+        if hash_move == Some(*chess_move) {
+            return HASH_MOVE_SCORE;
+        }
+        if is_tactical(board, chess_move) {
+            return CAPTURE_SCORE + mvv_lva(board, chess_move);
+        }
+        if let Some(killer) = self.killers[ply].iter().position(|killer| *killer == Some(*chess_move)) {
+            return KILLER_SCORES[killer];
+        }
+        let (start, end) = squares(chess_move);
+        self.history[color_index(board.player_to_move())][start][end]
+    }
+}
+
+impl Default for MoveOrderer {
+    fn default() -> Self {
+        MoveOrderer::new()
+    }
+}
+
+//Most valuable victim, least valuable attacker. Promotions count the gained material as the victim.
+pub fn mvv_lva(board: &Board, chess_move: &ChessMove) -> i32 {
+    let (start_x, start_y) = chess_move.start_pos;
+    let attacker = board.tile(start_x, start_y).piece;
+    let victim_value = captured_piece(board, chess_move).map_or(0, piece_value)
+        + chess_move.promotion.map_or(0, |piece| piece_value(piece) - piece_value(Piece::Pawn));
+    victim_value * 100 - piece_value(attacker)
+}
+
+//Captures and promotions, the moves the quiescence search looks at
+pub fn is_tactical(board: &Board, chess_move: &ChessMove) -> bool {
+    chess_move.promotion.is_some() || captured_piece(board, chess_move).is_some()
+}
+
+pub fn captured_piece(board: &Board, chess_move: &ChessMove) -> Option<Piece> {
+    let (start_x, start_y) = chess_move.start_pos;
+    let (end_x, end_y) = chess_move.end_pos;
+    let target = board.tile(end_x, end_y);
+    if target.color != Color::Empty {
+        return Some(target.piece);
+    }
+    //A pawn moving diagonally onto an empty square captures en passant
+    if board.tile(start_x, start_y).piece == Piece::Pawn && start_x != end_x {
+        return Some(Piece::Pawn);
+    }
+    None
+}
+
+fn squares(chess_move: &ChessMove) -> (usize, usize) {
+    let (start_x, start_y) = chess_move.start_pos;
+    let (end_x, end_y) = chess_move.end_pos;
+    (square_index(start_x, start_y), square_index(end_x, end_y))
+}
+
+#[test]
+fn captures_by_mvv_lva() {
+    let board = Board::from_fen("4k3/8/3q1r2/4P3/8/5Q2/8/4K3 w - - 0 1").unwrap();
+    let mut chess_moves = board.legal_moves();
+    MoveOrderer::new().order(&board, &mut chess_moves, None, 0);
+    assert_eq!(ChessMove::new((4, 4), (3, 5)), chess_moves[0]);
+    assert_eq!(ChessMove::new((4, 4), (5, 5)), chess_moves[1]);
+    assert_eq!(ChessMove::new((5, 2), (5, 5)), chess_moves[2]);
+}
+
+#[test]
+fn hash_move_then_killers_then_history() {
+    let board = Board::new();
+    let mut orderer = MoveOrderer::new();
+    let killer = ChessMove::new((6, 0), (5, 2));
+    let history_move = ChessMove::new((3, 1), (3, 3));
+    orderer.record_cutoff(&board, killer, 2, 1);
+    orderer.record_cutoff(&board, history_move, 3, 4);
+    let mut chess_moves = board.legal_moves();
+    let hash_move = ChessMove::new((4, 1), (4, 3));
+    orderer.order(&board, &mut chess_moves, Some(hash_move), 2);
+    assert_eq!(vec!(hash_move, killer, history_move), chess_moves[..3].to_vec());
+}
@@ -0,0 +1,99 @@
+use crate::chess::{board::Board, chess_move::ChessMove, color::Color, evaluation::{evaluate, piece_value}, game_result::GameResult, piece::Piece};
+use super::move_ordering::{captured_piece, mvv_lva};
+
+//A capture is skipped if even winning the captured piece for free leaves the side this far below its target
+const DELTA_MARGIN: f64 = 2.0;
+
+//Keeps searching captures and promotions until the position is quiet, so the evaluation is never taken in the
+//middle of an exchange. The score is from the point of view of color, which maximizes.
+//Every position reached in it is added to nodes.
+pub fn quiescence(board: &mut Board, mut alpha: f64, mut beta: f64, color: Color, delta_pruning: bool, nodes: &mut u64) -> f64 {
+    let static_evaluation = evaluate_for(board, color);
+    if board.result() != GameResult::Ongoing {
+        return static_evaluation;
+    }
+    let maximizing_player = board.player_to_move() == color;
+    //In check every move has to be looked at, standing still isn't an option
+    let in_check = board.is_check(board.player_to_move());
+    let mut chess_moves = board.legal_moves();
+    if !in_check {
+        //The side to move can stand pat, it doesn't have to capture if that makes things worse
+        if maximizing_player {
+            if static_evaluation >= beta {
+                return static_evaluation;
+            }
+            alpha = alpha.max(static_evaluation);
+        } else {
+            if static_evaluation <= alpha {
+                return static_evaluation;
+            }
+            beta = beta.min(static_evaluation);
+        }
+        chess_moves.retain(|chess_move| {
+            let gain = material_gain(board, chess_move);
+            let best_case = gain + DELTA_MARGIN;
+            gain > 0.0 && (!delta_pruning || if maximizing_player {static_evaluation + best_case > alpha} else {static_evaluation - best_case < beta})
+        });
+    }
+    chess_moves.sort_by_cached_key(|chess_move| -mvv_lva(board, chess_move));
+    let mut value = if in_check {
+        if maximizing_player {f64::MIN} else {f64::MAX}
+    } else {
+        static_evaluation
+    };
+    for chess_move in chess_moves {
+        let undo_info = board.make(chess_move);
+        *nodes += 1;
+        let node_value = quiescence(board, alpha, beta, color, delta_pruning, nodes);
+        board.unmake(chess_move, undo_info);
+        if maximizing_player {
+            value = value.max(node_value);
+            if value >= beta {
+                break;
+            }
+            alpha = alpha.max(value);
+        } else {
+            value = value.min(node_value);
+            if value <= alpha {
+                break;
+            }
+            beta = beta.min(value);
+        }
+    }
+    value
+}
+
+fn evaluate_for(board: &Board, color: Color) -> f64 {
+    match color {
+        Color::White => evaluate(board) as f64,
+        Color::Black => -evaluate(board) as f64,
+        _ => panic!("Can only evaluate for players")
+    }
+}
+
+//The material the move wins right away, the captured piece and what a pawn promotes to
+fn material_gain(board: &Board, chess_move: &ChessMove) -> f64 {
+    let promotion = chess_move.promotion.map_or(0, |piece| piece_value(piece) - piece_value(Piece::Pawn));
+    (captured_piece(board, chess_move).map_or(0, piece_value) + promotion) as f64
+}
+
+#[test]
+fn takes_hanging_piece() {
+    let mut board = Board::from_fen("4k3/8/8/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+    assert_eq!(9.0, quiescence(&mut board, f64::MIN, f64::MAX, Color::White, true, &mut 0));
+    assert_eq!(-9.0, quiescence(&mut board, f64::MIN, f64::MAX, Color::Black, true, &mut 0));
+}
+
+#[test]
+fn sees_recapture() {
+    let mut board = Board::from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+    let mut nodes = 0;
+    assert_eq!(7.0, quiescence(&mut board, f64::MIN, f64::MAX, Color::White, true, &mut nodes));
+    assert!(nodes > 1);
+}
+
+#[test]
+fn promotes() {
+    let mut board = Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    assert_eq!(9.0, quiescence(&mut board, f64::MIN, f64::MAX, Color::White, true, &mut 0));
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+use crate::chess::chess_move::ChessMove;
+use super::transposition_table::Bound;
+
+//What a finished search found, with the score from the searching player's point of view in pawns
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchInfo {
+    pub depth: i32,
+    pub score: f64,
+    //Exact for a finished iteration, a bound when the score fell outside the aspiration window and is searched again
+    pub bound: Bound,
+    pub principal_variation: Vec<ChessMove>,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    //How full the transposition table is in permille
+    pub hashfull: u32,
+    pub statistics: SearchStatistics
+}
+
+//How well the moves were ordered, ideally the first move searched causes the cutoff
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SearchStatistics {
+    pub beta_cutoffs: u64,
+    pub first_move_cutoffs: u64,
+    pub null_move_cutoffs: u64,
+    //Moves that beat alpha in a zero window or a reduced search and had to be searched again
+    pub zero_window_re_searches: u64,
+    pub reduction_re_searches: u64,
+    //Iterations searched again with a wider window because the score fell below or above the aspiration window
+    pub aspiration_fail_lows: u64,
+    pub aspiration_fail_highs: u64
+}
+
+impl SearchStatistics {
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        self.first_move_cutoffs as f64 / self.beta_cutoffs.max(1) as f64
+    }
+}
+
+impl SearchInfo {
+    pub fn best_move(&self) -> Option<ChessMove> {
+        self.principal_variation.first().copied()
+    }
+
+    pub fn nodes_per_second(&self) -> u64 {
+        let micros = self.elapsed.as_micros().max(1);
+        (self.nodes as u128 * 1_000_000 / micros) as u64
+    }
+}
+
+#[test]
+fn nodes_per_second() {
+    let info = SearchInfo {depth: 1, score: 0.0, bound: Bound::Exact, principal_variation: vec!(), nodes: 500, elapsed: Duration::from_millis(250), hashfull: 0,
+        statistics: SearchStatistics::default()};
+    assert_eq!(2000, info.nodes_per_second());
+    assert_eq!(None, info.best_move());
+}
+
+#[test]
+fn first_move_cutoff_rate() {
+    assert_eq!(0.0, SearchStatistics::default().first_move_cutoff_rate());
+    assert_eq!(0.75, SearchStatistics {beta_cutoffs: 4, first_move_cutoffs: 3, ..SearchStatistics::default()}.first_move_cutoff_rate());
+}
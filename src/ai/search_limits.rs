@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+pub const MAX_DEPTH: i32 = 64;
+//Check extensions can take the search past the depth of the iteration, but never further than this
+pub const MAX_PLY: usize = 2 * MAX_DEPTH as usize;
+
+//The searching side's clock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clock {
+    pub time_left: Duration,
+    pub increment: Duration,
+    //Moves until the next time control, None if the time has to last for the rest of the game
+    pub moves_to_go: Option<u32>
+}
+
+//The search ends at whichever limit it reaches first, without limits it goes on to MAX_DEPTH
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchLimits {
+    pub max_depth: i32,
+    pub max_nodes: Option<u64>,
+    pub move_time: Option<Duration>,
+    pub clock: Option<Clock>
+}
+
+impl SearchLimits {
+    pub fn depth(max_depth: i32) -> SearchLimits {
+        SearchLimits {max_depth, ..SearchLimits::default()}
+    }
+
+    pub fn move_time(move_time: Duration) -> SearchLimits {
+        SearchLimits {move_time: Some(move_time), ..SearchLimits::default()}
+    }
+
+    pub fn clock(clock: Clock) -> SearchLimits {
+        SearchLimits {clock: Some(clock), ..SearchLimits::default()}
+    }
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits {max_depth: MAX_DEPTH, max_nodes: None, move_time: None, clock: None}
+    }
+}
@@ -0,0 +1,35 @@
+//Scores are whole pawns, so the first window lets the score move by one pawn either way
+pub const DEFAULT_ASPIRATION_WINDOW: f64 = 1.5;
+
+//Switches for the search techniques, so their effect can be compared. All are on by default,
+//with everything off AlphaBetaAi finds the same scores as MinimaxAi.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchOptions {
+    //Skip captures in the quiescence search that can't get close to alpha even when they win material for free
+    pub delta_pruning: bool,
+    //Only prove that the moves after the first are worse with a zero window, and search them fully if they aren't
+    pub principal_variation_search: bool,
+    //If passing the turn still fails high, the position is good enough to skip with a shallower search
+    pub null_move_pruning: bool,
+    //Search late quiet moves one ply shallower, and again at full depth if they turn out better than expected
+    pub late_move_reductions: bool,
+    //Search one ply deeper after a check, so forcing lines aren't cut off at the horizon
+    pub check_extensions: bool,
+    //Half the width of the window around the previous iteration's score in pawns, it doubles every time the score
+    //falls outside. None searches every iteration with the full window.
+    pub aspiration_window: Option<f64>
+}
+
+impl SearchOptions {
+    pub fn exact() -> SearchOptions {
+        SearchOptions {delta_pruning: false, principal_variation_search: false, null_move_pruning: false, late_move_reductions: false,
+            check_extensions: false, aspiration_window: None}
+    }
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {delta_pruning: true, principal_variation_search: true, null_move_pruning: true, late_move_reductions: true,
+            check_extensions: true, aspiration_window: Some(DEFAULT_ASPIRATION_WINDOW)}
+    }
+}
@@ -0,0 +1,31 @@
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
+//Cancels a search from another thread. Clones share the flag, so a clone can go to whoever decides when to stop.
+#[derive(Debug, Clone, Default)]
+pub struct StopToken {
+    stopped: Arc<AtomicBool>
+}
+
+impl StopToken {
+    pub fn new() -> StopToken {
+        StopToken::default()
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+}
+
+#[test]
+fn clones_share_the_flag() {
+    let token = StopToken::new();
+    let clone = token.clone();
+    assert!(!token.is_stopped());
+    clone.stop();
+    assert!(token.is_stopped());
+    assert!(!StopToken::new().is_stopped());
+}
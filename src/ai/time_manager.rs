@@ -0,0 +1,73 @@
+use std::time::{Duration, Instant};
+use super::search_limits::SearchLimits;
+
+//Assumed when the clock has to last for the rest of the game
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+//Decides how long a search may take. The optimum time is spent by not starting iterations that
+//won't finish in time, the maximum time ends the iteration in progress.
+pub struct TimeManager {
+    start_time: Instant,
+    optimum: Option<Duration>,
+    maximum: Option<Duration>
+}
+
+impl TimeManager {
+    pub fn new(limits: &SearchLimits) -> TimeManager {
+        let (optimum, maximum) = match (limits.move_time, limits.clock) {
+            (Some(move_time), _) => (Some(move_time), Some(move_time)),
+            (None, Some(clock)) => {
+                let moves_to_go = clock.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+                //Some time is always left on the clock, even when this is the last move before the time control
+                let maximum = (clock.time_left * 3 / 4).min((clock.time_left / moves_to_go + clock.increment) * 3);
+                let optimum = (clock.time_left / moves_to_go + clock.increment / 2).min(maximum);
+                (Some(optimum), Some(maximum))
+            },
+            (None, None) => (None, None)
+        };
+        TimeManager {start_time: Instant::now(), optimum, maximum}
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    //The next iteration takes several times longer than the previous ones together, so don't start it late
+    pub fn can_start_iteration(&self) -> bool {
+        self.optimum.is_none_or(|optimum| self.elapsed() * 2 <= optimum)
+    }
+
+    pub fn out_of_time(&self) -> bool {
+        self.maximum.is_some_and(|maximum| self.elapsed() >= maximum)
+    }
+}
+
+#[cfg(test)]
+use super::search_limits::Clock;
+
+#[test]
+fn move_time_is_used_fully() {
+    let time_manager = TimeManager::new(&SearchLimits::move_time(Duration::from_millis(1500)));
+    assert_eq!((Some(Duration::from_millis(1500)), Some(Duration::from_millis(1500))), (time_manager.optimum, time_manager.maximum));
+    assert!(time_manager.can_start_iteration());
+    assert!(!time_manager.out_of_time());
+}
+
+#[test]
+fn clock_is_spread_over_moves_to_go() {
+    let clock = Clock {time_left: Duration::from_secs(60), increment: Duration::from_secs(1), moves_to_go: None};
+    let time_manager = TimeManager::new(&SearchLimits::clock(clock));
+    assert_eq!(Some(Duration::from_millis(2500)), time_manager.optimum);
+    assert_eq!(Some(Duration::from_secs(9)), time_manager.maximum);
+    let clock = Clock {time_left: Duration::from_secs(4), increment: Duration::from_secs(0), moves_to_go: Some(1)};
+    let time_manager = TimeManager::new(&SearchLimits::clock(clock));
+    assert_eq!(Some(Duration::from_secs(3)), time_manager.optimum);
+    assert_eq!(Some(Duration::from_secs(3)), time_manager.maximum);
+}
+
+#[test]
+fn no_time_limit() {
+    let time_manager = TimeManager::new(&SearchLimits::depth(5));
+    assert!(time_manager.can_start_iteration());
+    assert!(!time_manager.out_of_time());
+}
@@ -0,0 +1,208 @@
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use crate::chess::{bitboard::{piece_index, square_coordinates, square_index, PIECES}, chess_move::ChessMove};
+#[cfg(test)]
+use crate::chess::piece::Piece;
+
+pub const DEFAULT_SIZE_MB: usize = 16;
+//The whole table is allocated up front, so this is kept to what a usual machine can spare
+pub const MAX_SIZE_MB: usize = 1024;
+
+//Whether the stored score is the real value of the position or only a bound on it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    Exact,
+    //The search failed high, the position is worth at least the score
+    Lower,
+    //The search failed low, the position is worth at most the score
+    Upper
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entry {
+    pub key: u64,
+    pub depth: i32,
+    pub score: f64,
+    pub bound: Bound,
+    pub best_move: Option<ChessMove>,
+    generation: u8
+}
+
+//An entry packed into one word: the bound in the lowest 2 bits with 0 for an empty slot, then 8 bits of generation,
+//7 of depth, 15 of best move and the score as an f32 in the high half. Scores are whole or half pawns, which f32 holds exactly.
+impl Entry {
+    fn pack(&self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3
+        };
+        bound | (self.generation as u64) << 2 | (self.depth as u64 & 0x7F) << 10 | pack_move(self.best_move) << 17
+            | ((self.score as f32).to_bits() as u64) << 32
+    }
+
+    fn unpack(key: u64, data: u64) -> Option<Entry> {
+        let bound = match data & 0x3 {
+            0 => return None,
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            _ => Bound::Upper
+        };
+        Some(Entry {key, depth: (data >> 10 & 0x7F) as i32, score: f32::from_bits((data >> 32) as u32) as f64, bound,
+            best_move: unpack_move(data >> 17 & 0x7FFF), generation: (data >> 2) as u8})
+    }
+}
+
+//A move in 15 bits, 6 for each square and 3 for the promotion. No move is 0, a move never starts and ends on the same square.
+fn pack_move(chess_move: Option<ChessMove>) -> u64 {
+    chess_move.map_or(0, |chess_move| {
+        let (start_x, start_y) = chess_move.start_pos;
+        let (end_x, end_y) = chess_move.end_pos;
+        let promotion = chess_move.promotion.map_or(0, |piece| piece_index(piece) + 1);
+        (square_index(start_x, start_y) | square_index(end_x, end_y) << 6 | promotion << 12) as u64
+    })
+}
+
+fn unpack_move(bits: u64) -> Option<ChessMove> {
+    if bits == 0 {
+        return None;
+    }
+    let promotion = match bits >> 12 {
+        0 => None,
+        piece => Some(PIECES[piece as usize - 1])
+    };
+    Some(ChessMove {start_pos: square_coordinates((bits & 0x3F) as usize), end_pos: square_coordinates((bits >> 6 & 0x3F) as usize), promotion})
+}
+
+//The key is stored xored with the data, so an entry torn by two threads writing the slot at once doesn't match any key
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64
+}
+
+impl Slot {
+    fn load(&self) -> Option<Entry> {
+        let data = self.data.load(Ordering::Relaxed);
+        Entry::unpack(self.key.load(Ordering::Relaxed) ^ data, data)
+    }
+
+    fn save(&self, entry: &Entry) {
+        let data = entry.pack();
+        self.key.store(entry.key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+}
+
+//Results of earlier searches indexed by Zobrist key, one entry per slot. Threads share the table without locking.
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    generation: AtomicU8
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let slot_count = (size_mb * 1024 * 1024 / size_of::<Slot>()).max(1);
+        TranspositionTable {slots: (0..slot_count).map(|_| Slot {key: AtomicU64::new(0), data: AtomicU64::new(0)}).collect(), generation: AtomicU8::new(0)}
+    }
+
+    //Entries from earlier searches can still be probed, but are replaced before the current search's entries
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        self.slots.iter().for_each(|slot| {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        });
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.slots[self.index(key)].load().filter(|entry| entry.key == key)
+    }
+
+    //Keeps the deeper of two results of the current search, results of older searches are always replaced
+    pub fn store(&self, key: u64, depth: i32, score: f64, bound: Bound, best_move: Option<ChessMove>) {
+        let slot = &self.slots[self.index(key)];
+        let old_entry = slot.load();
+        let generation = self.generation.load(Ordering::Relaxed);
+        let replace = match old_entry {
+            None => true,
+            Some(entry) => entry.key == key || entry.generation != generation || depth >= entry.depth
+        };
+        if replace {
+            //A result without a best move still knows the best move found by an earlier search of the position
+            let best_move = best_move.or_else(|| old_entry.filter(|entry| entry.key == key).and_then(|entry| entry.best_move));
+            slot.save(&Entry {key, depth, score, bound, best_move, generation});
+        }
+    }
+
+    //How full the table is in permille, estimated from the first thousand slots like UCI engines do
+    pub fn hashfull(&self) -> u32 {
+        let sample = &self.slots[..self.slots.len().min(1000)];
+        let generation = self.generation.load(Ordering::Relaxed);
+        let used = sample.iter().filter_map(Slot::load).filter(|entry| entry.generation == generation).count();
+        (used * 1000 / sample.len()) as u32
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.slots.len() as u64) as usize
+    }
+}
+
+#[test]
+fn stores_and_probes() {
+    let table = TranspositionTable::new(1);
+    let chess_move = ChessMove::new((4, 1), (4, 3));
+    assert_eq!(None, table.probe(12345));
+    table.store(12345, 3, 1.5, Bound::Lower, Some(chess_move));
+    let entry = table.probe(12345).unwrap();
+    assert_eq!((3, 1.5, Bound::Lower, Some(chess_move)), (entry.depth, entry.score, entry.bound, entry.best_move));
+    table.store(12345, 4, 0.5, Bound::Upper, None);
+    assert_eq!(Some(chess_move), table.probe(12345).unwrap().best_move);
+    table.clear();
+    assert_eq!(None, table.probe(12345));
+}
+
+#[test]
+fn replacement_prefers_depth_then_new_searches() {
+    let table = TranspositionTable::new(1);
+    let slots = table.slots.len() as u64;
+    table.store(7, 5, 1.0, Bound::Exact, None);
+    table.store(7 + slots, 2, 2.0, Bound::Exact, None);
+    assert_eq!(5, table.probe(7).unwrap().depth);
+    assert_eq!(None, table.probe(7 + slots));
+    table.new_search();
+    table.store(7 + slots, 2, 2.0, Bound::Exact, None);
+    assert_eq!(2, table.probe(7 + slots).unwrap().depth);
+}
+
+#[test]
+fn hashfull_counts_current_search() {
+    let table = TranspositionTable::new(1);
+    assert_eq!(0, table.hashfull());
+    for key in 0..500 {
+        table.store(key, 1, 0.0, Bound::Exact, None);
+    }
+    assert_eq!(500, table.hashfull());
+    table.new_search();
+    assert_eq!(0, table.hashfull());
+}
+
+#[test]
+fn entries_survive_packing() {
+    let table = TranspositionTable::new(1);
+    let promotion = ChessMove {start_pos: (6, 6), end_pos: (7, 7), promotion: Some(Piece::Knight)};
+    table.store(u64::MAX, 64, -201.5, Bound::Upper, Some(promotion));
+    let entry = table.probe(u64::MAX).unwrap();
+    assert_eq!((64, -201.5, Bound::Upper, Some(promotion)), (entry.depth, entry.score, entry.bound, entry.best_move));
+}
+
+#[test]
+fn torn_entries_are_not_found() {
+    let table = TranspositionTable::new(1);
+    table.store(12345, 3, 1.0, Bound::Exact, None);
+    let slot = &table.slots[table.index(12345)];
+    slot.data.store(Entry {key: 12345, depth: 5, score: 2.0, bound: Bound::Lower, best_move: None, generation: 0}.pack(), Ordering::Relaxed);
+    assert_eq!(None, table.probe(12345));
+}
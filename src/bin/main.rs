@@ -1,25 +1,36 @@
+use std::env;
 use std::io::Write;
 use std::io::stdout;
 use std::io::stdin;
+use std::process;
+use std::time::{Duration, Instant};
 
 use chess_rust::chess::chess_move::ChessMove;
 use chess_rust::ai::alpha_beta_ai::AlphaBetaAi;
-use chess_rust::chess::color::Color;
+use chess_rust::ai::search_limits::SearchLimits;
 use chess_rust::game::game::Game;
 use chess_rust::ai::ai::Ai;
+use chess_rust::chess::board::Board;
 
+//How long the AI thinks about every move
+const THINKING_TIME: Duration = Duration::from_secs(5);
 
 fn main() {
     env_logger::init();
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("perft") {
+        perft(&args[2..]);
+        return;
+    }
     let mut game = Game::new();
-    let mut ai = AlphaBetaAi::new(Color::Black,9);
+    let mut ai = AlphaBetaAi::with_limits(SearchLimits::move_time(THINKING_TIME));
     loop {
         let mut input=String::new();
         println!("Please enter the next move: ");
         let _=stdout().flush();
         stdin().read_line(&mut input).expect("Did not enter a correct string");
         let chess_move_string = input.replace("\n", "");
-        let parsed_chess_move = ChessMove::from(chess_move_string);
+        let parsed_chess_move = ChessMove::from_uci(&chess_move_string).or_else(|| ChessMove::from(chess_move_string));
         match parsed_chess_move {
             None => println!("Incorrect move"),
             Some(chess_move) => make_move(&mut game, &chess_move, &mut ai)
@@ -36,3 +47,33 @@ fn make_move(game: &mut Game, chess_move: &ChessMove, ai: &mut dyn Ai) {
     let best_move = ai.find_best_move(&game.board);
     game.make_move(&best_move)
 }
+
+//Usage: perft <depth> [fen], prints the node count below every legal move and the total
+fn perft(args: &[String]) {
+    let depth = match args.first().and_then(|depth| depth.parse::<u32>().ok()) {
+        Some(depth) => depth,
+        None => {
+            eprintln!("Usage: perft <depth> [fen]");
+            process::exit(1);
+        }
+    };
+    let board = match args.len() {
+        1 => Board::new(),
+        _ => match Board::from_fen(&args[1..].join(" ")) {
+            Ok(board) => board,
+            Err(error) => {
+                eprintln!("Invalid FEN: {}", error);
+                process::exit(1);
+            }
+        }
+    };
+    let start_time = Instant::now();
+    let divide = board.divide(depth);
+    for (chess_move, nodes) in &divide {
+        println!("{}: {}", chess_move.to_uci(), nodes);
+    }
+    let nodes: u64 = if depth == 0 {1} else {divide.iter().map(|(_chess_move, nodes)| nodes).sum()};
+    println!();
+    println!("Nodes searched: {}", nodes);
+    println!("Time: {} ms", start_time.elapsed().as_millis());
+}
@@ -0,0 +1,256 @@
+use std::io::{stdin, stdout, BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chess_rust::ai::ai::Ai;
+use chess_rust::ai::alpha_beta_ai::{AlphaBetaAi, DECISIVE_SCORE, MATE_SCORE, MAX_THREADS};
+use chess_rust::ai::search_info::SearchInfo;
+use chess_rust::ai::search_limits::{Clock, SearchLimits, MAX_DEPTH};
+use chess_rust::ai::stop_token::StopToken;
+use chess_rust::ai::transposition_table::{self, Bound};
+use chess_rust::chess::board::Board;
+use chess_rust::chess::chess_move::ChessMove;
+use chess_rust::chess::color::Color;
+
+//Speaks the Universal Chess Interface over stdin and stdout
+fn main() {
+    env_logger::init();
+    let mut board = Board::new();
+    let mut search: Option<Search> = None;
+    //Kept between searches, so the transposition table is reused for the next move
+    let ai = Arc::new(Mutex::new(AlphaBetaAi::with_limits(SearchLimits::default())));
+    for line in stdin().lock().lines() {
+        let line = line.expect("Could not read from stdin");
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first() {
+            Some(&"uci") => {
+                println!("id name chess-rust");
+                println!("id author Malte Kauranen");
+                println!("option name Hash type spin default {} min 1 max {}", transposition_table::DEFAULT_SIZE_MB, transposition_table::MAX_SIZE_MB);
+                println!("option name Threads type spin default 1 min 1 max {}", MAX_THREADS);
+                println!("uciok");
+            },
+            Some(&"isready") => println!("readyok"),
+            Some(&"setoption") => {
+                finish_search(&mut search);
+                let mut ai = ai.lock().unwrap();
+                match parse_option(&tokens[1..]) {
+                    Some((name, value)) if name.eq_ignore_ascii_case("Hash") => match value.parse::<usize>() {
+                        Ok(size_mb) => ai.set_hash_size(size_mb.clamp(1, transposition_table::MAX_SIZE_MB)),
+                        Err(_) => eprintln!("Not a hash size: {}", value)
+                    },
+                    Some((name, value)) if name.eq_ignore_ascii_case("Threads") => match value.parse::<usize>() {
+                        Ok(threads) => ai.set_threads(threads),
+                        Err(_) => eprintln!("Not a thread count: {}", value)
+                    },
+                    _ => eprintln!("Unknown option: {}", line)
+                }
+            },
+            Some(&"ucinewgame") => {
+                finish_search(&mut search);
+                ai.lock().unwrap().clear_hash();
+                board = Board::new();
+            },
+            Some(&"position") => {
+                finish_search(&mut search);
+                match parse_position(&tokens[1..]) {
+                    Some(new_board) => board = new_board,
+                    None => eprintln!("Could not parse position: {}", line)
+                }
+            },
+            Some(&"go") => {
+                finish_search(&mut search);
+                let go_limits = GoLimits::parse(&tokens[1..], board.player_to_move());
+                search = Some(Search::start(ai.clone(), board.clone(), go_limits));
+            },
+            Some(&"stop") => {
+                if let Some(running_search) = &search {
+                    running_search.stop.stop();
+                }
+            },
+            Some(&"quit") => {
+                finish_search(&mut search);
+                break;
+            },
+            _ => ()
+        }
+        let _ = stdout().flush();
+    }
+    finish_search(&mut search);
+}
+
+struct Search {
+    stop: StopToken,
+    handle: JoinHandle<()>
+}
+
+impl Search {
+    fn start(ai: Arc<Mutex<AlphaBetaAi>>, board: Board, go_limits: GoLimits) -> Search {
+        let stop = StopToken::new();
+        let thread_stop = stop.clone();
+        let handle = thread::spawn(move || think(&mut ai.lock().unwrap(), &board, &go_limits, &thread_stop));
+        Search {stop, handle}
+    }
+}
+
+//A new command may only be handled once the previous search has printed its bestmove
+fn finish_search(search: &mut Option<Search>) {
+    if let Some(running_search) = search.take() {
+        running_search.stop.stop();
+        let _ = running_search.handle.join();
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct GoLimits {
+    limits: SearchLimits,
+    //The bestmove has to wait for a stop command
+    infinite: bool
+}
+
+impl GoLimits {
+    fn parse(tokens: &[&str], player_to_move: Color) -> GoLimits {
+        let value = |name: &str| -> Option<u64> {
+            let position = tokens.iter().position(|token| *token == name)?;
+            tokens.get(position + 1)?.parse::<i64>().ok().map(|value| value.max(0) as u64)
+        };
+        let (time_left, increment) = match player_to_move {
+            Color::Black => (value("btime"), value("binc")),
+            _ => (value("wtime"), value("winc"))
+        };
+        let clock = time_left.map(|time_left| Clock {
+            time_left: Duration::from_millis(time_left),
+            increment: Duration::from_millis(increment.unwrap_or(0)),
+            moves_to_go: value("movestogo").map(|moves_to_go| moves_to_go as u32)
+        });
+        let limits = SearchLimits {
+            max_depth: value("depth").map_or(MAX_DEPTH, |depth| (depth as i32).clamp(1, MAX_DEPTH)),
+            max_nodes: value("nodes"),
+            move_time: value("movetime").map(Duration::from_millis),
+            clock
+        };
+        GoLimits {limits, infinite: tokens.contains(&"infinite")}
+    }
+}
+
+fn think(ai: &mut AlphaBetaAi, board: &Board, go_limits: &GoLimits, stop: &StopToken) {
+    ai.set_limits(go_limits.limits.clone());
+    let search_info = ai.think(board, stop, &mut print_info);
+    let best_move = search_info.and_then(|search_info| search_info.best_move());
+    //In infinite mode the bestmove must wait for the stop command
+    while go_limits.infinite && !stop.is_stopped() {
+        thread::sleep(Duration::from_millis(5));
+    }
+    match best_move {
+        Some(chess_move) => println!("bestmove {}", chess_move.to_uci()),
+        None => println!("bestmove 0000")
+    }
+    let _ = stdout().flush();
+}
+
+fn print_info(search_info: &SearchInfo) {
+    let principal_variation: Vec<String> = search_info.principal_variation.iter().map(|chess_move| chess_move.to_uci()).collect();
+    let bound = match search_info.bound {
+        Bound::Exact => "",
+        Bound::Lower => " lowerbound",
+        Bound::Upper => " upperbound"
+    };
+    println!("info depth {} score {}{} nodes {} nps {} hashfull {} time {} pv {}", search_info.depth, format_score(search_info), bound,
+        search_info.nodes, search_info.nodes_per_second(), search_info.hashfull, search_info.elapsed.as_millis(), principal_variation.join(" "));
+    let _ = stdout().flush();
+}
+
+//Mates in moves, negative when the engine gets mated, and everything else in centipawns
+fn format_score(search_info: &SearchInfo) -> String {
+    if search_info.score.abs() < DECISIVE_SCORE {
+        return format!("cp {}", (search_info.score * 100.0).round() as i64);
+    }
+    //The score holds the depth left at the mate, extensions can make that more than the depth minus the plies to it
+    let plies = (search_info.depth - (search_info.score.abs() - MATE_SCORE).round() as i32).max(1);
+    format!("mate {}", search_info.score.signum() as i32 * (plies + 1) / 2)
+}
+
+//The name and value of "setoption name <name> value <value>", both may contain spaces
+fn parse_option(tokens: &[&str]) -> Option<(String, String)> {
+    let value_index = tokens.iter().position(|token| *token == "value").unwrap_or(tokens.len());
+    if tokens.first() != Some(&"name") || value_index == 1 {
+        return None;
+    }
+    let value = tokens.get(value_index + 1..).unwrap_or(&[]).join(" ");
+    Some((tokens[1..value_index].join(" "), value))
+}
+
+fn parse_position(tokens: &[&str]) -> Option<Board> {
+    let moves_index = tokens.iter().position(|token| *token == "moves").unwrap_or(tokens.len());
+    let mut board = match tokens.first() {
+        Some(&"startpos") => Board::new(),
+        Some(&"fen") => Board::from_fen(&tokens[1..moves_index].join(" ")).ok()?,
+        _ => return None
+    };
+    for move_string in tokens.iter().skip(moves_index + 1) {
+        let chess_move = ChessMove::from_uci(move_string)?;
+        if !board.legal_move_with_struct(&chess_move) {
+            return None;
+        }
+        board = board.make_move_with_struct(chess_move);
+    }
+    Some(board)
+}
+
+#[test]
+fn start_position_with_moves() {
+    let board = parse_position(&["startpos", "moves", "e2e4", "e7e5", "g1f3"]).unwrap();
+    assert_eq!("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2", board.to_fen());
+}
+
+#[test]
+fn fen_position_with_moves() {
+    let tokens: Vec<&str> = "fen 4k3/P7/8/8/8/8/8/4K3 w - - 0 1 moves a7a8n".split(' ').collect();
+    let board = parse_position(&tokens).unwrap();
+    assert_eq!("N3k3/8/8/8/8/8/8/4K3 b - - 0 1", board.to_fen());
+}
+
+#[test]
+fn illegal_moves_are_rejected() {
+    assert!(parse_position(&["startpos", "moves", "e2e5"]).is_none());
+    assert!(parse_position(&["nonsense"]).is_none());
+}
+
+#[test]
+fn options() {
+    assert_eq!(Some(("Threads".to_string(), "8".to_string())), parse_option(&["name", "Threads", "value", "8"]));
+    assert_eq!(Some(("Clear Hash".to_string(), String::new())), parse_option(&["name", "Clear", "Hash"]));
+    assert_eq!(None, parse_option(&["name", "value", "8"]));
+    assert_eq!(None, parse_option(&["Threads", "8"]));
+}
+
+#[test]
+fn go_limits() {
+    assert_eq!(GoLimits {limits: SearchLimits::depth(5), infinite: false}, GoLimits::parse(&["depth", "5"], Color::White));
+    assert_eq!(GoLimits {limits: SearchLimits::move_time(Duration::from_millis(1000)), infinite: false},
+        GoLimits::parse(&["movetime", "1000"], Color::White));
+    let clock = Clock {time_left: Duration::from_millis(60000), increment: Duration::from_millis(1000), moves_to_go: None};
+    assert_eq!(GoLimits {limits: SearchLimits::clock(clock), infinite: false},
+        GoLimits::parse(&["wtime", "1000", "btime", "60000", "winc", "0", "binc", "1000"], Color::Black));
+    assert_eq!(SearchLimits {max_nodes: Some(5000), ..SearchLimits::default()}, GoLimits::parse(&["nodes", "5000"], Color::White).limits);
+    assert_eq!(GoLimits {limits: SearchLimits::default(), infinite: true}, GoLimits::parse(&["infinite"], Color::White));
+}
+
+#[test]
+fn scores() {
+    let search_info = |depth: i32, score: f64| SearchInfo {depth, score, bound: Bound::Exact, principal_variation: vec!(), nodes: 0,
+        elapsed: Duration::from_millis(0), hashfull: 0, statistics: chess_rust::ai::search_info::SearchStatistics::default()};
+    assert_eq!("cp 125", format_score(&search_info(5, 1.25)));
+    assert_eq!("cp -300", format_score(&search_info(5, -3.0)));
+    assert_eq!("mate 1", format_score(&search_info(5, MATE_SCORE + 4.0)));
+    assert_eq!("mate 3", format_score(&search_info(5, MATE_SCORE)));
+    assert_eq!("mate -1", format_score(&search_info(5, -MATE_SCORE - 3.0)));
+    assert_eq!("mate -2", format_score(&search_info(5, -MATE_SCORE - 1.0)));
+}
+
+#[test]
+fn mate_is_found_and_formatted() {
+    let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1").unwrap();
+    assert_eq!("mate 1", format_score(&AlphaBetaAi::with_limits(SearchLimits::depth(4)).search(&board)));
+}
@@ -0,0 +1,391 @@
+use std::io::{stdin, stdout, BufRead, Write};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use chess_rust::ai::ai::Ai;
+use chess_rust::ai::alpha_beta_ai::AlphaBetaAi;
+use chess_rust::ai::search_info::SearchInfo;
+use chess_rust::ai::search_limits::{Clock, SearchLimits, MAX_DEPTH};
+use chess_rust::ai::stop_token::StopToken;
+use chess_rust::ai::transposition_table::Bound;
+use chess_rust::chess::board::Board;
+use chess_rust::chess::chess_move::ChessMove;
+use chess_rust::chess::color::Color;
+use chess_rust::chess::game_result::{GameResult, Termination};
+use chess_rust::game::game::Game;
+
+//Used when the interface sets neither a time control nor a depth
+const DEFAULT_DEPTH: i32 = 4;
+
+//Speaks the Chess Engine Communication Protocol (xboard/WinBoard version 2) over stdin and stdout
+fn main() {
+    env_logger::init();
+    let (sender, events) = mpsc::channel();
+    let mut engine = Engine::new(sender.clone());
+    //Commands are read on their own thread, so they can interrupt a search
+    thread::spawn(move || {
+        for line in stdin().lock().lines() {
+            let line = line.expect("Could not read from stdin");
+            if sender.send(Event::Command(line)).is_err() {
+                return;
+            }
+        }
+        let _ = sender.send(Event::Command("quit".to_string()));
+    });
+    let mut out = stdout();
+    for event in events {
+        if !engine.handle_event(event, &mut out) {
+            break;
+        }
+        let _ = out.flush();
+    }
+}
+
+//Commands from the interface and the progress of the engine's own search, handled one at a time
+enum Event {
+    Command(String),
+    //The id tells the searches apart, the results of an abandoned one are ignored
+    Thinking(u64, SearchInfo),
+    Done(u64, Option<SearchInfo>)
+}
+
+#[derive(Debug, PartialEq)]
+enum TimeControl {
+    Unlimited,
+    //Moves per session (0 for the whole game), base time and increment
+    Conventional(u32, Duration, Duration),
+    PerMove(Duration)
+}
+
+struct Engine {
+    game: Game,
+    //Kept between moves so the transposition table is reused
+    ai: Arc<Mutex<AlphaBetaAi>>,
+    events: Sender<Event>,
+    //The id and stop token of the search in progress
+    search: Option<(u64, StopToken)>,
+    searches_started: u64,
+    //None in force mode, where the engine only keeps track of the moves
+    engine_color: Option<Color>,
+    max_depth: Option<i32>,
+    time_control: TimeControl,
+    time_left: Option<Duration>,
+    post: bool
+}
+
+impl Engine {
+    fn new(events: Sender<Event>) -> Engine {
+        Engine {game: Game::new(), ai: Arc::new(Mutex::new(AlphaBetaAi::with_limits(SearchLimits::default()))), events,
+            search: None, searches_started: 0, engine_color: Some(Color::Black), max_depth: None, time_control: TimeControl::Unlimited,
+            time_left: None, post: false}
+    }
+
+    //Returns false once the interface has asked the engine to quit
+    fn handle_event(&mut self, event: Event, out: &mut dyn Write) -> bool {
+        match event {
+            Event::Command(line) => return self.handle(&line, out),
+            Event::Thinking(id, search_info) if self.is_searching(id) => self.post_thinking(&search_info, out),
+            Event::Done(id, search_info) if self.is_searching(id) => {
+                self.search = None;
+                self.play_engine_move(search_info, out);
+            },
+            _ => ()
+        }
+        true
+    }
+
+    //Returns false once the interface has asked the engine to quit
+    fn handle(&mut self, line: &str, out: &mut dyn Write) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let argument = |index: usize| tokens.get(index).copied().unwrap_or("");
+        //These change the game or whose move it is, which makes the search in progress useless
+        if matches!(argument(0), "new" | "force" | "go" | "usermove" | "setboard" | "undo" | "remove" | "result" | "quit") {
+            self.abandon_search();
+        }
+        match argument(0) {
+            "protover" => {
+                let _ = writeln!(out, "feature myname=\"chess-rust\" usermove=1 setboard=1 ping=1 sigint=0 sigterm=0 colors=0 done=1");
+            },
+            "new" => {
+                self.game = Game::new();
+                self.engine_color = Some(Color::Black);
+                self.max_depth = None;
+                self.ai.lock().unwrap().clear_hash();
+            },
+            "force" => self.engine_color = None,
+            "go" => {
+                self.engine_color = Some(self.game.board.player_to_move());
+                self.start_search();
+            },
+            //Move now, the search still plays the best move it has found
+            "?" => if let Some((_, stop)) = &self.search {
+                stop.stop();
+            },
+            "usermove" => self.user_move(argument(1), out),
+            "setboard" => match Board::from_fen(&tokens[1..].join(" ")) {
+                Ok(board) => self.game = Game::from_board(board),
+                Err(error) => {
+                    let _ = writeln!(out, "tellusererror Illegal position: {}", error);
+                }
+            },
+            "level" => match parse_level(&tokens[1..]) {
+                Some(time_control) => self.time_control = time_control,
+                None => {
+                    let _ = writeln!(out, "Error (bad level): {}", line);
+                }
+            },
+            "st" => match argument(1).parse::<u64>() {
+                Ok(seconds) => self.time_control = TimeControl::PerMove(Duration::from_secs(seconds)),
+                Err(_) => {
+                    let _ = writeln!(out, "Error (bad st): {}", line);
+                }
+            },
+            "sd" => match argument(1).parse::<i32>() {
+                Ok(depth) => self.max_depth = Some(depth.clamp(1, MAX_DEPTH)),
+                Err(_) => {
+                    let _ = writeln!(out, "Error (bad sd): {}", line);
+                }
+            },
+            //Both clocks are sent in centiseconds, only our own matters
+            "time" => self.time_left = argument(1).parse::<u64>().ok().map(|centiseconds| Duration::from_millis(centiseconds * 10)),
+            "undo" => {
+                self.game.undo_move();
+            },
+            "remove" => {
+                self.game.undo_move();
+                self.game.undo_move();
+            },
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "result" => self.engine_color = None,
+            "ping" => {
+                let _ = writeln!(out, "pong {}", argument(1));
+            },
+            "quit" => return false,
+            _ => ()
+        }
+        true
+    }
+
+    fn user_move(&mut self, move_string: &str, out: &mut dyn Write) {
+        let chess_move = match ChessMove::from_uci(move_string) {
+            Some(chess_move) if self.game.result() == &GameResult::Ongoing && self.game.legal_move(&chess_move) => chess_move,
+            _ => {
+                let _ = writeln!(out, "Illegal move: {}", move_string);
+                return;
+            }
+        };
+        self.game.make_move(&chess_move);
+        if self.report_result(out) {
+            return;
+        }
+        if self.engine_color == Some(self.game.board.player_to_move()) {
+            self.start_search();
+        }
+    }
+
+    //Searches on another thread, which reports back through the events
+    fn start_search(&mut self) {
+        if self.game.result() != &GameResult::Ongoing {
+            return;
+        }
+        self.searches_started += 1;
+        let id = self.searches_started;
+        let stop = StopToken::new();
+        self.search = Some((id, stop.clone()));
+        let (ai, events, board, limits) = (self.ai.clone(), self.events.clone(), self.game.board.clone(), self.search_limits());
+        thread::spawn(move || {
+            let mut ai = ai.lock().unwrap();
+            ai.set_limits(limits);
+            let search_info = ai.think(&board, &stop, &mut |search_info| {
+                let _ = events.send(Event::Thinking(id, search_info.clone()));
+            });
+            let _ = events.send(Event::Done(id, search_info));
+        });
+    }
+
+    fn is_searching(&self, id: u64) -> bool {
+        self.search.as_ref().is_some_and(|(search_id, _)| *search_id == id)
+    }
+
+    //Stops the search in progress and forgets about it, its move isn't played
+    fn abandon_search(&mut self) {
+        if let Some((_, stop)) = self.search.take() {
+            stop.stop();
+        }
+    }
+
+    fn post_thinking(&self, search_info: &SearchInfo, out: &mut dyn Write) {
+        //The protocol has no way to mark a score as a bound, so only finished iterations are posted
+        if self.post && search_info.bound == Bound::Exact {
+            let principal_variation: Vec<String> = search_info.principal_variation.iter().map(|chess_move| chess_move.to_uci()).collect();
+            let _ = writeln!(out, "{} {} {} {} {}", search_info.depth, (search_info.score * 100.0).round() as i64,
+                search_info.elapsed.as_millis() / 10, search_info.nodes, principal_variation.join(" "));
+        }
+    }
+
+    fn play_engine_move(&mut self, search_info: Option<SearchInfo>, out: &mut dyn Write) {
+        if let Some(best_move) = search_info.and_then(|search_info| search_info.best_move()) {
+            self.game.make_move(&best_move);
+            let _ = writeln!(out, "move {}", best_move.to_uci());
+            self.report_result(out);
+        }
+    }
+
+    fn search_limits(&self) -> SearchLimits {
+        let max_depth = match (self.max_depth, &self.time_control) {
+            (Some(depth), _) => depth,
+            (None, TimeControl::Unlimited) => DEFAULT_DEPTH,
+            (None, _) => MAX_DEPTH
+        };
+        let limits = SearchLimits {max_depth, ..SearchLimits::default()};
+        match self.time_control {
+            TimeControl::Unlimited => limits,
+            TimeControl::PerMove(move_time) => SearchLimits {move_time: Some(move_time), ..limits},
+            TimeControl::Conventional(moves_per_session, base, increment) => {
+                let moves_to_go = match moves_per_session {
+                    0 => None,
+                    _ => Some(moves_per_session - (self.game.board.fullmove_number() - 1) % moves_per_session)
+                };
+                let clock = Clock {time_left: self.time_left.unwrap_or(base), increment, moves_to_go};
+                SearchLimits {clock: Some(clock), ..limits}
+            }
+        }
+    }
+
+    //Returns true if the game is over
+    fn report_result(&self, out: &mut dyn Write) -> bool {
+        let result = match self.game.result() {
+            GameResult::WhiteWin(Termination::Checkmate) => "1-0 {White mates}".to_string(),
+            GameResult::BlackWin(Termination::Checkmate) => "0-1 {Black mates}".to_string(),
+            GameResult::Ongoing => return false,
+            result => format!("{} {{{}}}", result.pgn_result(), result)
+        };
+        let _ = writeln!(out, "{}", result);
+        true
+    }
+}
+
+//"level 40 5 0" is 40 moves in 5 minutes, "level 0 2:30 1" is the whole game in 2.5 minutes with one second increment
+fn parse_level(tokens: &[&str]) -> Option<TimeControl> {
+    if tokens.len() != 3 {
+        return None;
+    }
+    let moves_per_session = tokens[0].parse::<u32>().ok()?;
+    let mut base_parts = tokens[1].split(':');
+    let minutes = base_parts.next()?.parse::<u64>().ok()?;
+    let seconds = match base_parts.next() {
+        Some(seconds) => seconds.parse::<u64>().ok()?,
+        None => 0
+    };
+    let increment = tokens[2].parse::<f64>().ok().filter(|increment| *increment >= 0.0)?;
+    Some(TimeControl::Conventional(moves_per_session, Duration::from_secs(minutes * 60 + seconds), Duration::from_secs_f64(increment)))
+}
+
+#[cfg(test)]
+fn test_engine() -> (Engine, mpsc::Receiver<Event>) {
+    let (sender, events) = mpsc::channel();
+    (Engine::new(sender), events)
+}
+
+//Waits for the search a command starts before sending the next one
+#[cfg(test)]
+fn run(engine: &mut Engine, events: &mpsc::Receiver<Event>, commands: &[&str]) -> String {
+    let mut out = Vec::<u8>::new();
+    for command in commands {
+        engine.handle(command, &mut out);
+        while engine.search.is_some() {
+            engine.handle_event(events.recv().unwrap(), &mut out);
+        }
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn levels() {
+    assert_eq!(Some(TimeControl::Conventional(40, Duration::from_secs(300), Duration::from_secs(0))), parse_level(&["40", "5", "0"]));
+    assert_eq!(Some(TimeControl::Conventional(0, Duration::from_secs(150), Duration::from_secs(1))), parse_level(&["0", "2:30", "1"]));
+    assert_eq!(None, parse_level(&["0", "x", "1"]));
+    assert_eq!(None, parse_level(&["40", "5"]));
+}
+
+#[test]
+fn search_limits_follow_time_control() {
+    let (mut engine, events) = test_engine();
+    assert_eq!(SearchLimits::depth(DEFAULT_DEPTH), engine.search_limits());
+    run(&mut engine, &events, &["level 40 5 0", "time 12000"]);
+    let clock = Clock {time_left: Duration::from_secs(120), increment: Duration::from_secs(0), moves_to_go: Some(40)};
+    assert_eq!(SearchLimits::clock(clock), engine.search_limits());
+    run(&mut engine, &events, &["st 7", "sd 3"]);
+    assert_eq!(SearchLimits {max_depth: 3, ..SearchLimits::move_time(Duration::from_secs(7))}, engine.search_limits());
+}
+
+#[test]
+fn engine_replies_to_user_move() {
+    let (mut engine, events) = test_engine();
+    let output = run(&mut engine, &events, &["new", "sd 1", "usermove e2e4"]);
+    assert!(output.starts_with("move "));
+    assert_eq!(2, engine.game.moves().len());
+}
+
+#[test]
+fn force_mode_only_records_moves() {
+    let (mut engine, events) = test_engine();
+    let output = run(&mut engine, &events, &["new", "force", "usermove e2e4", "usermove e7e5", "ping 3"]);
+    assert_eq!("pong 3\n", output);
+    assert_eq!(2, engine.game.moves().len());
+    run(&mut engine, &events, &["undo"]);
+    assert_eq!(1, engine.game.moves().len());
+}
+
+#[test]
+fn illegal_moves_are_rejected() {
+    let (mut engine, events) = test_engine();
+    let output = run(&mut engine, &events, &["new", "force", "usermove e2e5"]);
+    assert_eq!("Illegal move: e2e5\n", output);
+}
+
+#[test]
+fn setboard_and_mate() {
+    let (mut engine, events) = test_engine();
+    let output = run(&mut engine, &events, &["new", "force", "setboard k7/8/1K6/8/8/8/8/6Q1 w - - 0 1", "sd 2", "post", "go"]);
+    assert!(output.contains("move g1g8\n"));
+    assert!(output.ends_with("1-0 {White mates}\n"));
+    let output = run(&mut engine, &events, &["setboard not a fen"]);
+    assert!(output.starts_with("tellusererror"));
+}
+
+#[test]
+fn move_now_plays_the_best_move_found() {
+    let (mut engine, events) = test_engine();
+    let mut out = Vec::<u8>::new();
+    for command in ["new", "st 1000", "go", "?"] {
+        engine.handle(command, &mut out);
+    }
+    while engine.search.is_some() {
+        engine.handle_event(events.recv().unwrap(), &mut out);
+    }
+    assert!(String::from_utf8(out).unwrap().starts_with("move "));
+    assert_eq!(1, engine.game.moves().len());
+}
+
+#[test]
+fn force_abandons_the_search() {
+    let (mut engine, events) = test_engine();
+    let mut out = Vec::<u8>::new();
+    for command in ["new", "st 1000", "go", "force"] {
+        engine.handle(command, &mut out);
+    }
+    loop {
+        let event = events.recv().unwrap();
+        let done = matches!(event, Event::Done(..));
+        engine.handle_event(event, &mut out);
+        if done {
+            break;
+        }
+    }
+    assert!(out.is_empty());
+    assert_eq!(0, engine.game.moves().len());
+}
@@ -0,0 +1,236 @@
+use super::{color::Color, piece::Piece};
+use std::sync::OnceLock;
+#[cfg(not(feature = "classical_attacks"))]
+use super::magic;
+
+//One bit per square, square index y * 8 + x, so a1 is the lowest bit and h8 the highest
+pub type Bitboard = u64;
+
+//a1 is a dark square, so the first rank is 0x55 and the second 0xAA
+pub const DARK_SQUARES: Bitboard = 0xAA55_AA55_AA55_AA55;
+pub const LIGHT_SQUARES: Bitboard = !DARK_SQUARES;
+
+//The pieces in the order of their bitboards in Board
+pub const PIECES: [Piece; 6] = [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King];
+
+pub fn square_index(x: usize, y: usize) -> usize {
+    y * 8 + x
+}
+
+pub fn square_coordinates(square: usize) -> (usize, usize) {
+    (square % 8, square / 8)
+}
+
+pub fn square_bit(square: usize) -> Bitboard {
+    1 << square
+}
+
+pub fn color_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+        Color::Empty => panic!("Empty squares have no bitboard")
+    }
+}
+
+pub fn piece_index(piece: Piece) -> usize {
+    match piece {
+        Piece::Pawn => 0,
+        Piece::Knight => 1,
+        Piece::Bishop => 2,
+        Piece::Rook => 3,
+        Piece::Queen => 4,
+        Piece::King => 5,
+        Piece::Empty => panic!("Empty squares have no bitboard")
+    }
+}
+
+//Iterates over the set squares from the lowest index up
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let square = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+}
+
+pub fn squares(bitboard: Bitboard) -> Squares {
+    Squares(bitboard)
+}
+
+//Directions for the sliding pieces, the first four move towards higher square indices
+const DIRECTIONS: [(i32, i32); 8] = [(0, 1), (1, 1), (1, 0), (-1, 1), (0, -1), (-1, -1), (-1, 0), (1, -1)];
+const BISHOP_DIRECTIONS: [usize; 4] = [1, 3, 5, 7];
+const ROOK_DIRECTIONS: [usize; 4] = [0, 2, 4, 6];
+
+struct AttackTables {
+    knight: [Bitboard; 64],
+    king: [Bitboard; 64],
+    //Indexed by the color of the attacking pawn
+    pawn: [[Bitboard; 64]; 2],
+    //Every square from the start square to the edge of the board in a direction
+    rays: [[Bitboard; 64]; 8]
+}
+
+static ATTACK_TABLES: OnceLock<AttackTables> = OnceLock::new();
+
+fn attack_tables() -> &'static AttackTables {
+    ATTACK_TABLES.get_or_init(AttackTables::new)
+}
+
+impl AttackTables {
+    //The leaping pieces reuse the move patterns in Piece, they don't depend on the rest of the board
+    fn new() -> AttackTables {
+        let mut tables = AttackTables {knight: [0; 64], king: [0; 64], pawn: [[0; 64]; 2], rays: [[0; 64]; 8]};
+        for square in 0..64 {
+            let (x, y) = square_coordinates(square);
+            tables.knight[square] = pattern_bitboard(Piece::Knight, x, y);
+            tables.king[square] = pattern_bitboard(Piece::King, x, y);
+            for (color, forward) in [(Color::White, 1), (Color::Black, -1)] {
+                tables.pawn[color_index(color)][square] = [-1, 1].iter()
+                .filter_map(|x_difference| offset_square(x, y, *x_difference, forward))
+                .fold(0, |attacks, target| attacks | square_bit(target));
+            }
+            for (direction, (x_step, y_step)) in DIRECTIONS.iter().enumerate() {
+                let mut distance = 1;
+                while let Some(target) = offset_square(x, y, x_step * distance, y_step * distance) {
+                    tables.rays[direction][square] |= square_bit(target);
+                    distance += 1;
+                }
+            }
+        }
+        tables
+    }
+}
+
+fn pattern_bitboard(piece: Piece, x: usize, y: usize) -> Bitboard {
+    piece.squares_that_fit_move_pattern(x, y).into_iter()
+    .fold(0, |pattern, (end_x, end_y)| pattern | square_bit(square_index(end_x, end_y)))
+}
+
+fn offset_square(x: usize, y: usize, x_difference: i32, y_difference: i32) -> Option<usize> {
+    let end_x = x as i32 + x_difference;
+    let end_y = y as i32 + y_difference;
+    if (0..8).contains(&end_x) && (0..8).contains(&end_y) {
+        Some(square_index(end_x as usize, end_y as usize))
+    } else {
+        None
+    }
+}
+
+pub fn knight_attacks(square: usize) -> Bitboard {
+    attack_tables().knight[square]
+}
+
+pub fn king_attacks(square: usize) -> Bitboard {
+    attack_tables().king[square]
+}
+
+//The squares a pawn of the given color standing on the square attacks
+pub fn pawn_attacks(color: Color, square: usize) -> Bitboard {
+    attack_tables().pawn[color_index(color)][square]
+}
+
+//The ray stops at the first occupied square, which is included whether it holds a friend or a foe
+fn ray_attacks(direction: usize, square: usize, occupied: Bitboard) -> Bitboard {
+    let rays = &attack_tables().rays[direction];
+    let ray = rays[square];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+    let first_blocker = if direction < 4 {
+        blockers.trailing_zeros() as usize
+    } else {
+        63 - blockers.leading_zeros() as usize
+    };
+    ray ^ rays[first_blocker]
+}
+
+//Walks the rays one direction at a time, the magic tables are built from these
+pub fn classical_bishop_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    BISHOP_DIRECTIONS.iter().fold(0, |attacks, direction| attacks | ray_attacks(*direction, square, occupied))
+}
+
+pub fn classical_rook_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    ROOK_DIRECTIONS.iter().fold(0, |attacks, direction| attacks | ray_attacks(*direction, square, occupied))
+}
+
+#[cfg(not(feature = "classical_attacks"))]
+pub fn bishop_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    magic::bishop_attacks(square, occupied)
+}
+
+#[cfg(not(feature = "classical_attacks"))]
+pub fn rook_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    magic::rook_attacks(square, occupied)
+}
+
+#[cfg(feature = "classical_attacks")]
+pub fn bishop_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    classical_bishop_attacks(square, occupied)
+}
+
+#[cfg(feature = "classical_attacks")]
+pub fn rook_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    classical_rook_attacks(square, occupied)
+}
+
+pub fn queen_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    bishop_attacks(square, occupied) | rook_attacks(square, occupied)
+}
+
+//The squares strictly between two squares on a line, empty if they don't share a line
+pub fn between(start: usize, end: usize) -> Bitboard {
+    let rays = &attack_tables().rays;
+    (0..8).find(|direction| rays[*direction][start] & square_bit(end) != 0)
+    .map_or(0, |direction| rays[direction][start] & !rays[direction][end] & !square_bit(end))
+}
+
+#[test]
+fn knight_and_king_tables() {
+    assert_eq!(square_bit(square_index(1, 2)) | square_bit(square_index(2, 1)), knight_attacks(0));
+    assert_eq!(8, king_attacks(square_index(4, 4)).count_ones());
+    assert_eq!(3, king_attacks(square_index(7, 7)).count_ones());
+}
+
+#[test]
+fn pawn_tables() {
+    assert_eq!(square_bit(square_index(3, 2)) | square_bit(square_index(5, 2)), pawn_attacks(Color::White, square_index(4, 1)));
+    assert_eq!(square_bit(square_index(1, 5)), pawn_attacks(Color::Black, square_index(0, 6)));
+}
+
+#[test]
+fn sliding_attacks_stop_at_blockers() {
+    let occupied = square_bit(square_index(0, 3)) | square_bit(square_index(3, 3));
+    let attacks = rook_attacks(0, occupied);
+    assert_eq!(7 + 3, attacks.count_ones());
+    assert!(attacks & square_bit(square_index(0, 3)) != 0);
+    assert!(attacks & square_bit(square_index(0, 4)) == 0);
+    assert_eq!(3, bishop_attacks(0, occupied).count_ones());
+    assert_eq!(13, queen_attacks(0, occupied).count_ones());
+}
+
+#[test]
+fn squares_between() {
+    assert_eq!(square_bit(square_index(5, 0)) | square_bit(square_index(6, 0)), between(square_index(4, 0), square_index(7, 0)));
+    assert_eq!(square_bit(square_index(1, 1)), between(square_index(2, 2), 0));
+    assert_eq!(0, between(0, square_index(1, 2)));
+    assert_eq!(squares(LIGHT_SQUARES).count(), 32);
+}
+
+#[test]
+fn square_colors() {
+    assert_ne!(0, DARK_SQUARES & square_bit(square_index(0, 0)));
+    assert_ne!(0, DARK_SQUARES & square_bit(square_index(7, 7)));
+    assert_ne!(0, LIGHT_SQUARES & square_bit(square_index(7, 0)));
+    assert_ne!(0, LIGHT_SQUARES & square_bit(square_index(3, 0)));
+    assert_eq!(squares(DARK_SQUARES).count(), 32);
+}
@@ -1,10 +1,35 @@
-use super::{chess_move::ChessMove, color::Color, game_result::GameResult, move_generator::MoveGenerator, piece::Piece, tile::Tile};
-use std::{fmt};
+use super::{bitboard::{self, Bitboard, color_index, piece_index, square_bit, square_index, squares}, castling_rights::CastlingRights, chess_move::ChessMove, fen::{self, FenError}, color::Color, game_result::{DrawRule, GameResult, Termination}, move_generator::MoveGenerator, piece::Piece, square::square_name, tile::Tile};
+use super::zobrist;
+use std::{fmt, hash::{Hash, Hasher}};
 
 #[derive(Debug, Clone)]
 pub struct Board {
-    pub tiles: Vec<Vec<Tile>>,
-    player_to_move: Color
+    //One bitboard per color and piece, indexed with bitboard::color_index and bitboard::piece_index
+    pieces: [[Bitboard; 6]; 2],
+    occupancy: [Bitboard; 2],
+    //The same position indexed by square, for looking up what stands on a square
+    mailbox: [Tile; 64],
+    player_to_move: Color,
+    castling_rights: CastlingRights,
+    en_passant: Option<(usize, usize)>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    //Keys of the earlier positions, only the ones since the last capture or pawn move can repeat
+    position_history: Vec<u64>,
+    draw_rule: DrawRule,
+    //Zobrist key of the position, kept up to date by every change to the board
+    hash: u64
+}
+
+//What make overwrote, unmake needs it to restore the position
+#[derive(Debug, Clone, Copy)]
+pub struct UndoInfo {
+    moved: Tile,
+    captured: Tile,
+    castling_rights: CastlingRights,
+    en_passant: Option<(usize, usize)>,
+    halfmove_clock: u32,
+    fullmove_number: u32
 }
 
 fn get_piece_from_column(x: usize) -> Piece {
@@ -34,93 +59,394 @@ fn get_piece_for_starting_tile( x: usize, y: usize ) -> Tile {
 impl Board {
     //Standard chess board
     pub fn new() -> Board{
-        let mut tiles = Vec::<Vec::<Tile>>::new();
+        let mut board = Board::empty();
         for x in 0..8 {
-            tiles.push(Vec::<Tile>::new());
             for y in 0..8 {
-                tiles[x].push(get_piece_for_starting_tile(x, y));
+                board.set_tile(x, y, get_piece_for_starting_tile(x, y));
             }
         }
-        Board {tiles, player_to_move: Color::White}
+        board.castling_rights = CastlingRights::all();
+        board.hash = board.compute_hash();
+        board
     }
     //Empty chess board for tests or other purposes
     pub fn empty() -> Board{
-        let mut tiles = Vec::<Vec::<Tile>>::new();
-        for x in 0..8 {
-            tiles.push(Vec::<Tile>::new());
-            for _y in 0..8 {
-                tiles[x].push(Tile{piece: Piece::Empty, color: Color::Empty});
-            }
+        Board {pieces: [[0; 6]; 2], occupancy: [0; 2], mailbox: [Tile::new(Color::Empty, Piece::Empty); 64],
+            player_to_move: Color::White, castling_rights: CastlingRights::none(), en_passant: None,
+            halfmove_clock: 0, fullmove_number: 1, position_history: Vec::new(), draw_rule: DrawRule::Claimed, hash: 0}
+    }
+
+    //Clocks are optional, positions copied from EPD lines only have the first four fields
+    pub fn from_fen(fen_string: &str) -> Result<Board, FenError> {
+        let fields: Vec<&str> = fen_string.split_whitespace().collect();
+        if fields.len() != 4 && fields.len() != 6 {
+            return Err(FenError::WrongNumberOfFields(fields.len()));
+        }
+        let player_to_move = fen::parse_side_to_move(fields[1])?;
+        let (halfmove_clock, fullmove_number) = match fields.len() {
+            6 => (fen::parse_halfmove_clock(fields[4])?, fen::parse_fullmove_number(fields[5])?),
+            _ => (0, 1)
+        };
+        let mut board = Board::empty();
+        for (square, tile) in fen::parse_piece_placement(fields[0])?.iter().enumerate() {
+            let (x, y) = bitboard::square_coordinates(square);
+            board.set_tile(x, y, *tile);
+        }
+        board.player_to_move = player_to_move;
+        board.castling_rights = fen::parse_castling_rights(fields[2])?;
+        board.en_passant = fen::parse_en_passant(fields[3], player_to_move)?;
+        board.halfmove_clock = halfmove_clock;
+        board.fullmove_number = fullmove_number;
+        board.hash = board.compute_hash();
+        Ok(board)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let en_passant = match self.en_passant {
+            Some(square) => square_name(square),
+            None => "-".to_string()
+        };
+        format!("{} {} {} {} {} {}", fen::piece_placement(&self.mailbox), fen::side_to_move(self.player_to_move),
+            fen::castling_rights(&self.castling_rights), en_passant, self.halfmove_clock, self.fullmove_number)
+    }
+
+    pub fn tile(&self, x: usize, y: usize) -> Tile {
+        self.mailbox[square_index(x, y)]
+    }
+
+    //Replaces whatever stood on the square, an empty tile clears it
+    pub fn set_tile(&mut self, x: usize, y: usize, tile: Tile) {
+        //Whether the en passant square counts depends on the pawns next to it
+        self.hash ^= self.en_passant_hash();
+        self.place(x, y, tile);
+        self.hash ^= self.en_passant_hash();
+    }
+
+    fn place(&mut self, x: usize, y: usize, tile: Tile) {
+        let square = square_index(x, y);
+        let old_tile = self.mailbox[square];
+        if old_tile.piece != Piece::Empty && old_tile.color != Color::Empty {
+            self.pieces[color_index(old_tile.color)][piece_index(old_tile.piece)] &= !square_bit(square);
+            self.occupancy[color_index(old_tile.color)] &= !square_bit(square);
+            self.hash ^= zobrist::piece_key(old_tile.color, old_tile.piece, square);
+        }
+        if tile.piece == Piece::Empty || tile.color == Color::Empty {
+            self.mailbox[square] = Tile::new(Color::Empty, Piece::Empty);
+            return;
+        }
+        self.pieces[color_index(tile.color)][piece_index(tile.piece)] |= square_bit(square);
+        self.occupancy[color_index(tile.color)] |= square_bit(square);
+        self.hash ^= zobrist::piece_key(tile.color, tile.piece, square);
+        self.mailbox[square] = tile;
+    }
+
+    pub fn pieces(&self, color: Color, piece: Piece) -> Bitboard {
+        self.pieces[color_index(color)][piece_index(piece)]
+    }
+
+    pub fn occupancy(&self, color: Color) -> Bitboard {
+        self.occupancy[color_index(color)]
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.occupancy[0] | self.occupancy[1]
+    }
+
+    pub fn player_to_move(&self) -> Color {
+        self.player_to_move
+    }
+
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling_rights
+    }
+
+    //The square a pawn skipped over with a double push on the previous move
+    pub fn en_passant(&self) -> Option<(usize, usize)> {
+        self.en_passant
+    }
+
+    //Half moves since the last capture or pawn move
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    pub fn set_draw_rule(&mut self, draw_rule: DrawRule) {
+        self.draw_rule = draw_rule;
+    }
+
+    //Positions are the same if the same player has the same moves available, so the en passant
+    //file only counts when there is a pawn that could capture on it
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    fn compute_hash(&self) -> u64 {
+        let mut hash = squares(self.occupied())
+        .fold(0, |hash, square| hash ^ zobrist::piece_key(self.mailbox[square].color, self.mailbox[square].piece, square));
+        if self.player_to_move == Color::Black {
+            hash ^= zobrist::black_to_move_key();
+        }
+        hash ^ zobrist::castling_key(self.castling_rights) ^ self.en_passant_hash()
+    }
+
+    fn en_passant_hash(&self) -> u64 {
+        self.capturable_en_passant().map_or(0, |(x, _y)| zobrist::en_passant_key(x))
+    }
+
+    fn capturable_en_passant(&self) -> Option<(usize, usize)> {
+        let (x, y) = self.en_passant?;
+        let capturing_pawns = bitboard::pawn_attacks(Color::opposing_color(self.player_to_move), square_index(x, y))
+            & self.pieces(self.player_to_move, Piece::Pawn);
+        if capturing_pawns != 0 {Some((x, y))} else {None}
+    }
+
+    //How many times the current position has occurred, counting this one
+    pub fn repetition_count(&self) -> usize {
+        1 + self.position_history.iter().rev().take(self.halfmove_clock as usize)
+        .filter(|earlier_key| **earlier_key == self.hash).count()
+    }
+
+    //The search treats a single repetition as a draw, repeating once more can't be better for either side
+    pub fn is_repetition(&self) -> bool {
+        self.repetition_count() > 1
+    }
+
+    pub fn claimable_draw(&self) -> Option<Termination> {
+        if self.repetition_count() >= 3 {
+            Some(Termination::ThreefoldRepetition)
+        } else if self.halfmove_clock >= 100 {
+            Some(Termination::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    //Neither side can ever mate with lone kings, a single minor piece or bishops that all stand on one color
+    pub fn insufficient_material(&self) -> bool {
+        let both_colors = |piece: Piece| self.pieces(Color::White, piece) | self.pieces(Color::Black, piece);
+        if both_colors(Piece::Pawn) | both_colors(Piece::Rook) | both_colors(Piece::Queen) != 0 {
+            return false;
+        }
+        let knights = both_colors(Piece::Knight);
+        let bishops = both_colors(Piece::Bishop);
+        (knights | bishops).count_ones() <= 1
+            || (knights == 0 && (bishops & bitboard::LIGHT_SQUARES == 0 || bishops & bitboard::DARK_SQUARES == 0))
+    }
+
+    fn automatic_draw(&self) -> Option<Termination> {
+        if self.insufficient_material() {
+            Some(Termination::InsufficientMaterial)
+        } else if self.repetition_count() >= 5 {
+            Some(Termination::FivefoldRepetition)
+        } else if self.halfmove_clock >= 150 {
+            Some(Termination::SeventyFiveMoveRule)
+        } else {
+            None
         }
-        Board {tiles, player_to_move: Color::White}
     }
 
     pub fn make_move(&self, start_x: usize,start_y: usize, end_x: usize, end_y: usize) -> Board {
-        let mut new_board =self.clone();
-        new_board.player_to_move = Color::opposing_color(self.player_to_move);
-        new_board.tiles[end_x][end_y] = self.tiles[start_x][start_y];
-        new_board.tiles[start_x][start_y] = Tile::new(Color::Empty, Piece::Empty);
-        new_board
+        self.make_move_with_struct(ChessMove::new((start_x, start_y), (end_x, end_y)))
     }
 
+    //A pawn reaching the last rank without a promotion piece becomes a queen
     pub fn make_move_with_struct(&self, chess_move: ChessMove) -> Board {
-        self.make_move(chess_move.get_start_x(), chess_move.get_start_y(), chess_move.get_end_x(), chess_move.get_end_y())
+        let mut new_board = self.clone();
+        new_board.make(chess_move);
+        new_board
+    }
+
+    //Plays the move in place, the search uses this with unmake instead of copying the board for every node
+    pub fn make(&mut self, chess_move: ChessMove) -> UndoInfo {
+        let (start_x, start_y) = chess_move.start_pos;
+        let (end_x, end_y) = chess_move.end_pos;
+        let empty = Tile::new(Color::Empty, Piece::Empty);
+        let moving = self.tile(start_x, start_y);
+        //A pawn moving diagonally onto the en passant square captures the pawn that passed it
+        let en_passant_capture = moving.piece == Piece::Pawn && start_x != end_x && self.en_passant == Some((end_x, end_y));
+        let captured_y = if en_passant_capture {start_y} else {end_y};
+        let undo_info = UndoInfo {moved: moving, captured: self.tile(end_x, captured_y), castling_rights: self.castling_rights,
+            en_passant: self.en_passant, halfmove_clock: self.halfmove_clock, fullmove_number: self.fullmove_number};
+        self.position_history.push(self.hash);
+        self.hash ^= zobrist::castling_key(self.castling_rights) ^ self.en_passant_hash();
+        self.place(end_x, captured_y, empty);
+        self.place(end_x, end_y, moving);
+        self.place(start_x, start_y, empty);
+        //Castling is the only king move covering two files, the rook jumps over to the other side
+        if is_castling(moving, chess_move) {
+            let (rook_start_x, rook_end_x) = castling_rook_files(end_x);
+            self.place(rook_end_x, end_y, self.tile(rook_start_x, start_y));
+            self.place(rook_start_x, start_y, empty);
+        }
+        self.en_passant = None;
+        if moving.piece == Piece::Pawn && start_x == end_x && (start_y as i128 - end_y as i128).abs() == 2 {
+            self.en_passant = Some((start_x, (start_y + end_y) / 2));
+        }
+        if moving.piece == Piece::Pawn && (end_y == 0 || end_y == 7) {
+            self.place(end_x, end_y, Tile::new(moving.color, chess_move.promotion.unwrap_or(Piece::Queen)));
+        }
+        self.castling_rights.update_for_move((start_x, start_y), (end_x, end_y));
+        if undo_info.captured.piece != Piece::Empty || moving.piece == Piece::Pawn {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.player_to_move == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.player_to_move = Color::opposing_color(self.player_to_move);
+        self.hash ^= zobrist::black_to_move_key() ^ zobrist::castling_key(self.castling_rights) ^ self.en_passant_hash();
+        undo_info
+    }
+
+    //Takes back a move played with make, given the UndoInfo make returned for it
+    pub fn unmake(&mut self, chess_move: ChessMove, undo_info: UndoInfo) {
+        let (start_x, start_y) = chess_move.start_pos;
+        let (end_x, end_y) = chess_move.end_pos;
+        let empty = Tile::new(Color::Empty, Piece::Empty);
+        //The key before the move is still in the history, so it doesn't need to be rebuilt
+        let restored_hash = self.position_history.pop().expect("Unmaking a move that was never made");
+        self.player_to_move = Color::opposing_color(self.player_to_move);
+        self.place(end_x, end_y, empty);
+        self.place(start_x, start_y, undo_info.moved);
+        let en_passant_capture = undo_info.moved.piece == Piece::Pawn && start_x != end_x && undo_info.en_passant == Some((end_x, end_y));
+        let captured_y = if en_passant_capture {start_y} else {end_y};
+        self.place(end_x, captured_y, undo_info.captured);
+        if is_castling(undo_info.moved, chess_move) {
+            let (rook_start_x, rook_end_x) = castling_rook_files(end_x);
+            self.place(rook_start_x, start_y, self.tile(rook_end_x, end_y));
+            self.place(rook_end_x, end_y, empty);
+        }
+        self.castling_rights = undo_info.castling_rights;
+        self.en_passant = undo_info.en_passant;
+        self.halfmove_clock = undo_info.halfmove_clock;
+        self.fullmove_number = undo_info.fullmove_number;
+        self.hash = restored_hash;
+    }
+
+    //Passes the turn for null move pruning. Positions before it can't repeat until it is unmade, the other side didn't really get to move.
+    pub fn make_null_move(&mut self) -> UndoInfo {
+        let empty = Tile::new(Color::Empty, Piece::Empty);
+        let undo_info = UndoInfo {moved: empty, captured: empty, castling_rights: self.castling_rights,
+            en_passant: self.en_passant, halfmove_clock: self.halfmove_clock, fullmove_number: self.fullmove_number};
+        self.position_history.push(self.hash);
+        self.hash ^= self.en_passant_hash() ^ zobrist::black_to_move_key();
+        self.en_passant = None;
+        self.halfmove_clock = 0;
+        self.player_to_move = Color::opposing_color(self.player_to_move);
+        undo_info
+    }
+
+    pub fn unmake_null_move(&mut self, undo_info: UndoInfo) {
+        self.hash = self.position_history.pop().expect("Unmaking a null move that was never made");
+        self.player_to_move = Color::opposing_color(self.player_to_move);
+        self.en_passant = undo_info.en_passant;
+        self.halfmove_clock = undo_info.halfmove_clock;
     }
 
     pub fn legal_move(&self, start_x: usize,start_y: usize, end_x: usize, end_y: usize) -> bool {
-        let color_before_move = self.tiles[start_x][start_y].color;
+        let color_before_move = self.tile(start_x, start_y).color;
         if color_before_move != self.player_to_move {
             return false;
         }
-        if MoveGenerator::new(self).piece_can_reach(start_x, start_y, end_x, end_y) {
-            //Check if resulting position is check for the player that moved
-            let new_board = self.make_move(start_x, start_y, end_x, end_y);
-            return !new_board.is_check(color_before_move);
+        let move_generator = MoveGenerator::new(self);
+        move_generator.piece_can_reach(start_x, start_y, end_x, end_y)
+            && !move_generator.leaves_king_in_check(&ChessMove::new((start_x, start_y), (end_x, end_y)))
+    }
+
+    pub fn legal_move_with_struct(&self, chess_move: &ChessMove) -> bool {
+        let (start_x, start_y) = chess_move.start_pos;
+        let (end_x, end_y) = chess_move.end_pos;
+        if let Some(promotion) = chess_move.promotion {
+            let promoting = self.tile(start_x, start_y).piece == Piece::Pawn && (end_y == 0 || end_y == 7);
+            if !promoting || !promotion.is_promotion_piece() {
+                return false;
+            }
         }
-        false
+        self.legal_move(start_x, start_y, end_x, end_y)
     }
 
     pub fn result(&self) -> GameResult {
         if self.legal_moves().is_empty() {
             if self.player_to_move == Color::White {
                 if self.is_check(Color::White){
-                    return GameResult::BlackWin
+                    return GameResult::BlackWin(Termination::Checkmate)
                 } 
             } else if self.is_check(Color::Black) {
-                    return GameResult::WhiteWin
+                    return GameResult::WhiteWin(Termination::Checkmate)
                 } 
-                return GameResult::Draw
+                return GameResult::Draw(Termination::Stalemate)
 
         }
+        if let Some(termination) = self.automatic_draw() {
+            return GameResult::Draw(termination);
+        }
+        if self.draw_rule == DrawRule::Claimed {
+            if let Some(termination) = self.claimable_draw() {
+                return GameResult::Draw(termination);
+            }
+        }
         GameResult::Ongoing
     }
 
+    //The generated moves already follow the piece rules, only the own king being left in check remains to filter
     pub fn legal_moves(&self) -> Vec<ChessMove> {
-        MoveGenerator::new(self).possible_moves().into_iter()
-        .filter(|chess_move| self.legal_move(chess_move.start_pos.0, chess_move.start_pos.1, chess_move.end_pos.0, chess_move.end_pos.1))
+        let move_generator = MoveGenerator::new(self);
+        move_generator.possible_moves().into_iter()
+        .filter(|chess_move| !move_generator.leaves_king_in_check(chess_move))
         .collect()
     }
 
 
 
-    fn is_check(&self, color_to_check: Color) -> bool {
-        let king_position: Option<(usize, usize)> = self.all_squares().into_iter().find(|(x ,y)| self.tiles[*x][*y].color == color_to_check && self.tiles[*x][*y].piece == Piece::King);
-        let (king_x, king_y) = king_position.unwrap();
-        self.all_squares().into_iter().any(|(x, y)| self.tiles[x][y].color != color_to_check && MoveGenerator::new(self).piece_can_reach(x, y, king_x, king_y))
+    pub fn is_check(&self, color_to_check: Color) -> bool {
+        let king_square = squares(self.pieces(color_to_check, Piece::King)).next().expect("No king on the board");
+        let (king_x, king_y) = bitboard::square_coordinates(king_square);
+        MoveGenerator::new(self).square_attacked(king_x, king_y, Color::opposing_color(color_to_check))
     }
 
     pub fn all_squares(&self) -> Vec<(usize, usize)> {
-        let mut squares = Vec::<(usize, usize)>::new();
-        for x in 0..self.tiles.len() {
-            for y in 0..self.tiles[x].len() {
-                squares.push((x, y));
-            }
-        }
-        squares
+        (0..8).flat_map(|x| (0..8).map(move |y| (x, y))).collect()
+    }
+}
+
+
+fn is_castling(moving: Tile, chess_move: ChessMove) -> bool {
+    let (start_x, start_y) = chess_move.start_pos;
+    let (end_x, end_y) = chess_move.end_pos;
+    moving.piece == Piece::King && start_x == 4 && (start_y == 0 || start_y == 7) && start_y == end_y && (end_x == 6 || end_x == 2)
+}
+
+//The rook's start and end file when the king castles to the given file
+fn castling_rook_files(king_end_x: usize) -> (usize, usize) {
+    if king_end_x == 6 {(7, 5)} else {(0, 3)}
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new()
+    }
+}
+
+//Boards are equal when they are the same position, the move clocks and history don't matter, like in hash
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.pieces == other.pieces && self.player_to_move == other.player_to_move
+            && self.castling_rights == other.castling_rights && self.capturable_en_passant() == other.capturable_en_passant()
     }
 }
 
+impl Eq for Board {}
 
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
 
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -128,7 +454,7 @@ impl fmt::Display for Board {
         for y in 0..8 {
             let mut row_string = String::new();
             for x in 0..8 {
-                row_string += self.tiles[x][y].get_symbol_for_tile();
+                row_string += self.tile(x, y).get_symbol_for_tile();
             }
             board_string.insert(0, '\n');
             board_string.insert_str(0, row_string.as_str());
@@ -431,9 +757,9 @@ fn pinned_piece_can_check(){
 #[test]
 fn can_take_queen(){
     let mut board = Board::empty();
-    board.tiles[1][0] = Tile{piece: Piece::King, color: Color::White};
-    board.tiles[7][7] = Tile{piece: Piece::King, color: Color::Black};
-    board.tiles[2][1] = Tile{piece: Piece::Queen, color: Color::Black};
+    board.set_tile(1, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(7, 7, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(2, 1, Tile{piece: Piece::Queen, color: Color::Black});
     assert!(board.legal_move(1, 0, 2, 1));
     assert!(board.legal_move(1, 0, 0, 0));
     assert_eq!(2, board.legal_moves().len())
@@ -453,7 +779,7 @@ fn scholars_mate(){
     assert_eq!(GameResult::Ongoing, board.result());
     board = board.make_move(5, 2, 5, 6);
     assert!(board.legal_moves().is_empty());
-    assert_eq!(GameResult::WhiteWin, board.result());
+    assert_eq!(GameResult::WhiteWin(Termination::Checkmate), board.result());
 }
 
 
@@ -461,9 +787,439 @@ fn scholars_mate(){
 #[test]
 fn draw(){
     let mut board = Board::empty();
-    board.tiles[0][0] = Tile{piece: Piece::King, color: Color::White};
-    board.tiles[0][5] = Tile{piece: Piece::King, color: Color::Black};
-    board.tiles[2][1] = Tile{piece: Piece::Queen, color: Color::Black};
-    assert_eq!(GameResult::Draw, board.result());
+    board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(0, 5, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(2, 1, Tile{piece: Piece::Queen, color: Color::Black});
+    assert_eq!(GameResult::Draw(Termination::Stalemate), board.result());
 }
 
+#[test]
+fn castle_king_side(){
+    let mut board = Board::new();
+    board = board.make_move(4, 1, 4, 3);
+    board = board.make_move(4, 6, 4, 4);
+    board = board.make_move(6, 0, 5, 2);
+    board = board.make_move(6, 7, 5, 5);
+    board = board.make_move(5, 0, 2, 3);
+    board = board.make_move(5, 7, 2, 4);
+    assert!(board.legal_move(4, 0, 6, 0));
+    assert!(board.legal_moves().contains(&ChessMove::new((4, 0), (6, 0))));
+    board = board.make_move(4, 0, 6, 0);
+    assert_eq!(Piece::King, board.tile(6, 0).piece);
+    assert_eq!(Piece::Rook, board.tile(5, 0).piece);
+    assert_eq!(Piece::Empty, board.tile(7, 0).piece);
+    assert!(!board.castling_rights().king_side(Color::White));
+    assert!(!board.castling_rights().queen_side(Color::White));
+    assert!(board.legal_move(4, 7, 6, 7));
+}
+
+#[test]
+fn castle_queen_side(){
+    let mut board = Board::new();
+    board = board.make_move(3, 1, 3, 3);
+    board = board.make_move(3, 6, 3, 4);
+    board = board.make_move(1, 0, 2, 2);
+    board = board.make_move(1, 7, 2, 5);
+    board = board.make_move(2, 0, 5, 3);
+    board = board.make_move(2, 7, 5, 4);
+    board = board.make_move(3, 0, 3, 1);
+    board = board.make_move(3, 7, 3, 6);
+    assert!(board.legal_move(4, 0, 2, 0));
+    board = board.make_move(4, 0, 2, 0);
+    assert_eq!(Piece::King, board.tile(2, 0).piece);
+    assert_eq!(Piece::Rook, board.tile(3, 0).piece);
+    assert_eq!(Piece::Empty, board.tile(0, 0).piece);
+    assert!(board.legal_move(4, 7, 2, 7));
+    board = board.make_move(4, 7, 2, 7);
+    assert_eq!(Piece::King, board.tile(2, 7).piece);
+    assert_eq!(Piece::Rook, board.tile(3, 7).piece);
+}
+
+#[test]
+fn castle_blocked(){
+    let board = Board::new();
+    assert!(!board.legal_move(4, 0, 6, 0));
+    assert!(!board.legal_move(4, 0, 2, 0));
+}
+
+#[test]
+fn castle_after_king_moved(){
+    let mut board = Board::empty();
+    board.set_tile(4, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(7, 0, Tile{piece: Piece::Rook, color: Color::White});
+    board.set_tile(4, 7, Tile{piece: Piece::King, color: Color::Black});
+    board.castling_rights = CastlingRights::all();
+    assert!(board.legal_move(4, 0, 6, 0));
+    board = board.make_move(4, 0, 4, 1);
+    board = board.make_move(4, 7, 4, 6);
+    board = board.make_move(4, 1, 4, 0);
+    board = board.make_move(4, 6, 4, 7);
+    assert!(!board.legal_move(4, 0, 6, 0));
+}
+
+#[test]
+fn castle_after_rook_moved(){
+    let mut board = Board::empty();
+    board.set_tile(4, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(7, 0, Tile{piece: Piece::Rook, color: Color::White});
+    board.set_tile(0, 0, Tile{piece: Piece::Rook, color: Color::White});
+    board.set_tile(4, 7, Tile{piece: Piece::King, color: Color::Black});
+    board.castling_rights = CastlingRights::all();
+    board = board.make_move(7, 0, 7, 1);
+    board = board.make_move(4, 7, 4, 6);
+    board = board.make_move(7, 1, 7, 0);
+    board = board.make_move(4, 6, 4, 7);
+    assert!(!board.legal_move(4, 0, 6, 0));
+    assert!(board.legal_move(4, 0, 2, 0));
+}
+
+#[test]
+fn castle_out_of_check(){
+    let mut board = Board::empty();
+    board.set_tile(4, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(7, 0, Tile{piece: Piece::Rook, color: Color::White});
+    board.set_tile(4, 7, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(4, 5, Tile{piece: Piece::Rook, color: Color::Black});
+    board.castling_rights = CastlingRights::all();
+    assert!(!board.legal_move(4, 0, 6, 0));
+}
+
+#[test]
+fn castle_through_check(){
+    let mut board = Board::empty();
+    board.set_tile(4, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(7, 0, Tile{piece: Piece::Rook, color: Color::White});
+    board.set_tile(0, 0, Tile{piece: Piece::Rook, color: Color::White});
+    board.set_tile(4, 7, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(5, 5, Tile{piece: Piece::Rook, color: Color::Black});
+    board.castling_rights = CastlingRights::all();
+    assert!(!board.legal_move(4, 0, 6, 0));
+    assert!(board.legal_move(4, 0, 2, 0));
+}
+
+#[test]
+fn castle_into_check(){
+    let mut board = Board::empty();
+    board.set_tile(4, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(7, 0, Tile{piece: Piece::Rook, color: Color::White});
+    board.set_tile(0, 0, Tile{piece: Piece::Rook, color: Color::White});
+    board.set_tile(4, 7, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(6, 5, Tile{piece: Piece::Rook, color: Color::Black});
+    board.castling_rights = CastlingRights::all();
+    assert!(!board.legal_move(4, 0, 6, 0));
+    assert!(board.legal_move(4, 0, 2, 0));
+}
+
+#[test]
+fn castle_queen_side_with_attacked_rook_path(){
+    //Only the squares the king crosses need to be safe, b1 may be attacked
+    let mut board = Board::empty();
+    board.set_tile(4, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(0, 0, Tile{piece: Piece::Rook, color: Color::White});
+    board.set_tile(4, 7, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(1, 5, Tile{piece: Piece::Rook, color: Color::Black});
+    board.castling_rights = CastlingRights::all();
+    assert!(board.legal_move(4, 0, 2, 0));
+}
+
+#[test]
+fn en_passant_square_after_double_push(){
+    let mut board = Board::new();
+    assert_eq!(None, board.en_passant());
+    board = board.make_move(4, 1, 4, 3);
+    assert_eq!(Some((4, 2)), board.en_passant());
+    board = board.make_move(4, 6, 4, 5);
+    assert_eq!(None, board.en_passant());
+}
+
+#[test]
+fn en_passant_capture(){
+    let mut board = Board::new();
+    board = board.make_move(4, 1, 4, 3);
+    board = board.make_move(0, 6, 0, 5);
+    board = board.make_move(4, 3, 4, 4);
+    board = board.make_move(3, 6, 3, 4);
+    assert!(board.legal_move(4, 4, 3, 5));
+    assert!(board.legal_moves().contains(&ChessMove::new((4, 4), (3, 5))));
+    board = board.make_move(4, 4, 3, 5);
+    assert_eq!(Piece::Pawn, board.tile(3, 5).piece);
+    assert_eq!(Piece::Empty, board.tile(3, 4).piece);
+    assert_eq!(Piece::Empty, board.tile(4, 4).piece);
+}
+
+#[test]
+fn en_passant_black_capture(){
+    let mut board = Board::new();
+    board = board.make_move(0, 1, 0, 2);
+    board = board.make_move(3, 6, 3, 4);
+    board = board.make_move(0, 2, 0, 3);
+    board = board.make_move(3, 4, 3, 3);
+    board = board.make_move(2, 1, 2, 3);
+    assert!(board.legal_move(3, 3, 2, 2));
+    board = board.make_move(3, 3, 2, 2);
+    assert_eq!(Color::Black, board.tile(2, 2).color);
+    assert_eq!(Piece::Empty, board.tile(2, 3).piece);
+}
+
+#[test]
+fn en_passant_only_directly_after_double_push(){
+    let mut board = Board::new();
+    board = board.make_move(4, 1, 4, 3);
+    board = board.make_move(0, 6, 0, 5);
+    board = board.make_move(4, 3, 4, 4);
+    board = board.make_move(3, 6, 3, 4);
+    board = board.make_move(0, 1, 0, 2);
+    board = board.make_move(0, 5, 0, 4);
+    assert!(!board.legal_move(4, 4, 3, 5));
+}
+
+#[test]
+fn en_passant_exposing_king_on_rank(){
+    //Both pawns leave the fifth rank, which would open it for the rook
+    let mut board = Board::empty();
+    board.set_tile(0, 4, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(4, 4, Tile{piece: Piece::Pawn, color: Color::White});
+    board.set_tile(3, 6, Tile{piece: Piece::Pawn, color: Color::Black});
+    board.set_tile(7, 4, Tile{piece: Piece::Rook, color: Color::Black});
+    board.set_tile(7, 7, Tile{piece: Piece::King, color: Color::Black});
+    board.player_to_move = Color::Black;
+    board = board.make_move(3, 6, 3, 4);
+    assert_eq!(Some((3, 5)), board.en_passant());
+    assert!(!board.legal_move(4, 4, 3, 5));
+    assert!(board.legal_move(4, 4, 4, 5));
+}
+
+#[test]
+fn promotion_moves_generated(){
+    let mut board = Board::empty();
+    board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(7, 7, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(2, 6, Tile{piece: Piece::Pawn, color: Color::White});
+    let promotions: Vec<ChessMove> = board.legal_moves().into_iter().filter(|chess_move| chess_move.start_pos == (2, 6)).collect();
+    assert_eq!(4, promotions.len());
+    for piece in [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
+        assert!(promotions.contains(&ChessMove{start_pos: (2, 6), end_pos: (2, 7), promotion: Some(piece)}));
+    }
+}
+
+#[test]
+fn promotion_replaces_pawn(){
+    let mut board = Board::empty();
+    board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(7, 7, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(2, 6, Tile{piece: Piece::Pawn, color: Color::White});
+    board.set_tile(1, 7, Tile{piece: Piece::Rook, color: Color::Black});
+    let capture_promotion = ChessMove{start_pos: (2, 6), end_pos: (1, 7), promotion: Some(Piece::Knight)};
+    assert!(board.legal_move_with_struct(&capture_promotion));
+    board = board.make_move_with_struct(capture_promotion);
+    assert_eq!(Piece::Knight, board.tile(1, 7).piece);
+    assert_eq!(Color::White, board.tile(1, 7).color);
+    assert_eq!(Piece::Empty, board.tile(2, 6).piece);
+}
+
+#[test]
+fn black_promotion_defaults_to_queen(){
+    let mut board = Board::empty();
+    board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(7, 7, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(4, 1, Tile{piece: Piece::Pawn, color: Color::Black});
+    board.player_to_move = Color::Black;
+    board = board.make_move(4, 1, 4, 0);
+    assert_eq!(Piece::Queen, board.tile(4, 0).piece);
+    assert_eq!(Color::Black, board.tile(4, 0).color);
+}
+
+#[test]
+fn promotion_piece_on_normal_move(){
+    let board = Board::new();
+    let chess_move = ChessMove{start_pos: (4, 1), end_pos: (4, 3), promotion: Some(Piece::Queen)};
+    assert!(!board.legal_move_with_struct(&chess_move));
+}
+
+#[test]
+fn starting_position_fen(){
+    assert_eq!(fen::STARTING_POSITION, Board::new().to_fen());
+    let board = Board::from_fen(fen::STARTING_POSITION).unwrap();
+    assert_eq!(20, board.legal_moves().len());
+    assert_eq!(Board::new().castling_rights(), board.castling_rights());
+}
+
+#[test]
+fn fen_round_trip(){
+    let fens = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 37",
+        "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 3 20"
+    ];
+    for fen_string in fens {
+        assert_eq!(fen_string, Board::from_fen(fen_string).unwrap().to_fen());
+    }
+}
+
+#[test]
+fn fen_without_clocks(){
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+    assert_eq!(Color::Black, board.player_to_move());
+    assert_eq!("4k3/8/8/8/8/8/8/4K3 b - - 0 1", board.to_fen());
+}
+
+#[test]
+fn fen_follows_moves(){
+    let mut board = Board::new();
+    board = board.make_move(4, 1, 4, 3);
+    assert_eq!("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", board.to_fen());
+    board = board.make_move(2, 6, 2, 4);
+    assert_eq!("rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2", board.to_fen());
+    board = board.make_move(6, 0, 5, 2);
+    assert_eq!("rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2", board.to_fen());
+    board = board.make_move(3, 7, 0, 4);
+    board = board.make_move(5, 2, 4, 4);
+    assert_eq!(3, board.halfmove_clock());
+    board = board.make_move(0, 4, 3, 1);
+    assert_eq!(0, board.halfmove_clock());
+    assert_eq!(4, board.fullmove_number());
+}
+
+#[test]
+fn fen_errors(){
+    assert_eq!(Err(FenError::WrongNumberOfFields(1)), Board::from_fen("8/8/8/8/8/8/8/8").map(|board| board.to_fen()));
+    assert!(matches!(Board::from_fen("8/8/8/8/8/8/8/8 w - - 0 1"), Err(FenError::InvalidKingCount)));
+    assert!(matches!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 x - - 0 1"), Err(FenError::InvalidSideToMove(_))));
+    assert!(matches!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - e3 0 1"), Err(FenError::InvalidEnPassant(_))));
+    assert!(matches!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - x 1"), Err(FenError::InvalidHalfmoveClock(_))));
+    assert!(matches!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 0"), Err(FenError::InvalidFullmoveNumber(_))));
+}
+
+#[test]
+fn threefold_repetition(){
+    let mut board = Board::new();
+    for _ in 0..2 {
+        assert_eq!(GameResult::Ongoing, board.result());
+        board = board.make_move(6, 0, 5, 2);
+        board = board.make_move(6, 7, 5, 5);
+        board = board.make_move(5, 2, 6, 0);
+        board = board.make_move(5, 5, 6, 7);
+    }
+    assert_eq!(3, board.repetition_count());
+    assert_eq!(GameResult::Draw(Termination::ThreefoldRepetition), board.result());
+    board = board.make_move(4, 1, 4, 3);
+    assert_eq!(1, board.repetition_count());
+}
+
+#[test]
+fn en_passant_only_matters_when_capturable(){
+    let with_square = Board::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").unwrap();
+    let without_square = Board::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1").unwrap();
+    assert_eq!(with_square.hash(), without_square.hash());
+    let with_square = Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+    let without_square = Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap();
+    assert_ne!(with_square.hash(), without_square.hash());
+}
+
+#[test]
+fn fifty_move_rule(){
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+    assert_eq!(GameResult::Ongoing, board.result());
+    let board = board.make_move(0, 0, 0, 1);
+    assert_eq!(Some(Termination::FiftyMoveRule), board.claimable_draw());
+    assert_eq!(GameResult::Draw(Termination::FiftyMoveRule), board.result());
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 150 80").unwrap();
+    assert_eq!(GameResult::Draw(Termination::SeventyFiveMoveRule), board.result());
+}
+
+#[test]
+fn automatic_draws_ignore_claims(){
+    let mut board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 120 80").unwrap();
+    board.set_draw_rule(DrawRule::Automatic);
+    assert_eq!(Some(Termination::FiftyMoveRule), board.claimable_draw());
+    assert_eq!(GameResult::Ongoing, board.result());
+}
+
+#[test]
+fn insufficient_material(){
+    for fen in ["4k3/8/8/8/8/8/8/4K3 w - - 0 1", "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1", "4k3/8/8/8/8/8/8/1N2K3 b - - 0 1",
+        "3bk3/8/8/8/8/8/8/2B1K3 w - - 0 1", "4k3/8/8/8/8/8/8/B1B1K3 w - - 0 1"] {
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(GameResult::Draw(Termination::InsufficientMaterial), board.result(), "{}", fen);
+    }
+    for fen in ["2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1", "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1", "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+        "n3k3/8/8/8/8/8/8/1N2K3 w - - 0 1"] {
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(GameResult::Ongoing, board.result(), "{}", fen);
+    }
+}
+
+#[test]
+fn unmake_restores_position(){
+    for fen in ["r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "1r5k/P7/8/8/8/8/8/K7 w - - 0 1", "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 5 20"] {
+        let mut board = Board::from_fen(fen).unwrap();
+        for chess_move in board.legal_moves() {
+            let undo_info = board.make(chess_move);
+            assert_ne!(fen, board.to_fen());
+            board.unmake(chess_move, undo_info);
+            assert_eq!(fen, board.to_fen());
+            assert_eq!(Board::from_fen(fen).unwrap().occupied(), board.occupied());
+        }
+    }
+}
+
+#[test]
+fn hash_is_updated_incrementally(){
+    for fen in ["r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"] {
+        let mut board = Board::from_fen(fen).unwrap();
+        for chess_move in board.legal_moves() {
+            let hash_before = board.hash();
+            let undo_info = board.make(chess_move);
+            assert_eq!(board.compute_hash(), board.hash(), "{} after {}", fen, chess_move);
+            for reply in board.legal_moves() {
+                let reply_undo_info = board.make(reply);
+                assert_eq!(board.compute_hash(), board.hash(), "{} after {} {}", fen, chess_move, reply);
+                board.unmake(reply, reply_undo_info);
+            }
+            board.unmake(chess_move, undo_info);
+            assert_eq!(hash_before, board.hash());
+        }
+    }
+}
+
+#[test]
+fn transpositions_are_equal(){
+    let mut board = Board::new();
+    board = board.make_move(6, 0, 5, 2);
+    board = board.make_move(6, 7, 5, 5);
+    board = board.make_move(1, 0, 2, 2);
+    let mut other_board = Board::new();
+    other_board = other_board.make_move(1, 0, 2, 2);
+    other_board = other_board.make_move(6, 7, 5, 5);
+    other_board = other_board.make_move(6, 0, 5, 2);
+    assert_eq!(board.hash(), other_board.hash());
+    assert_eq!(board, other_board);
+    assert_ne!(board, Board::new());
+    let positions: std::collections::HashSet<Board> = vec!(board, other_board, Board::new()).into_iter().collect();
+    assert_eq!(2, positions.len());
+}
+
+#[test]
+fn set_tile_updates_hash(){
+    let mut board = Board::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").unwrap();
+    board.set_tile(3, 3, Tile::new(Color::Black, Piece::Pawn));
+    assert_eq!(board.compute_hash(), board.hash());
+    assert_eq!(Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap().hash(), board.hash());
+}
+
+#[test]
+fn null_move_passes_the_turn(){
+    let mut board = Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+    let fen_before = board.to_fen();
+    let hash_before = board.hash();
+    let undo_info = board.make_null_move();
+    assert_eq!(Color::White, board.player_to_move());
+    assert_eq!(None, board.en_passant());
+    assert_eq!(board.compute_hash(), board.hash());
+    board.unmake_null_move(undo_info);
+    assert_eq!(fen_before, board.to_fen());
+    assert_eq!(hash_before, board.hash());
+}
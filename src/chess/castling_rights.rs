@@ -0,0 +1,86 @@
+use super::color::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool
+}
+
+impl CastlingRights {
+    pub fn all() -> CastlingRights {
+        CastlingRights {white_king_side: true, white_queen_side: true, black_king_side: true, black_queen_side: true}
+    }
+
+    pub fn none() -> CastlingRights {
+        CastlingRights {white_king_side: false, white_queen_side: false, black_king_side: false, black_queen_side: false}
+    }
+
+    pub fn king_side(&self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_king_side,
+            Color::Black => self.black_king_side,
+            Color::Empty => false
+        }
+    }
+
+    pub fn queen_side(&self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_queen_side,
+            Color::Black => self.black_queen_side,
+            Color::Empty => false
+        }
+    }
+
+    //A move from or to one of the king or rook starting squares removes the rights tied to that square,
+    //which covers king moves, rook moves and rooks being captured at home
+    pub fn update_for_move(&mut self, start_pos: (usize, usize), end_pos: (usize, usize)) {
+        self.remove_for_square(start_pos);
+        self.remove_for_square(end_pos);
+    }
+
+    fn remove_for_square(&mut self, square: (usize, usize)) {
+        match square {
+            (4, 0) => {
+                self.white_king_side = false;
+                self.white_queen_side = false;
+            },
+            (7, 0) => self.white_king_side = false,
+            (0, 0) => self.white_queen_side = false,
+            (4, 7) => {
+                self.black_king_side = false;
+                self.black_queen_side = false;
+            },
+            (7, 7) => self.black_king_side = false,
+            (0, 7) => self.black_queen_side = false,
+            _ => ()
+        }
+    }
+}
+
+#[test]
+fn king_move_removes_both_rights() {
+    let mut rights = CastlingRights::all();
+    rights.update_for_move((4, 0), (4, 1));
+    assert!(!rights.king_side(Color::White));
+    assert!(!rights.queen_side(Color::White));
+    assert!(rights.king_side(Color::Black));
+    assert!(rights.queen_side(Color::Black));
+}
+
+#[test]
+fn rook_move_removes_one_right() {
+    let mut rights = CastlingRights::all();
+    rights.update_for_move((0, 7), (0, 5));
+    assert!(rights.king_side(Color::Black));
+    assert!(!rights.queen_side(Color::Black));
+}
+
+#[test]
+fn rook_captured_removes_right() {
+    let mut rights = CastlingRights::all();
+    rights.update_for_move((1, 6), (7, 0));
+    assert!(!rights.king_side(Color::White));
+    assert!(rights.queen_side(Color::White));
+}
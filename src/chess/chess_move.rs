@@ -1,17 +1,26 @@
-use std::fmt::{Display, self};
+use std::fmt;
+use super::{piece::Piece, square::{parse_square, square_name}};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ChessMove {
     pub start_pos: (usize, usize),
     pub end_pos: (usize, usize),
+    pub promotion: Option<Piece>
 }
 
 impl ChessMove {
+    pub fn new(start_pos: (usize, usize), end_pos: (usize, usize)) -> ChessMove {
+        ChessMove {start_pos, end_pos, promotion: None}
+    }
+
+    //Parses "start_x start_y end_x end_y", optionally followed by the promotion piece, e.g. "4 6 4 7 q"
     pub fn from(chess_move_string: String) -> Option<ChessMove> {
-        let index_strings = chess_move_string.split(' ');
-        if chess_move_string.split(' ').count() != 4 {
-            return None;
-        }
+        let mut index_strings: Vec<&str> = chess_move_string.split(' ').collect();
+        let promotion = match index_strings.len() {
+            4 => None,
+            5 => Some(parse_promotion(index_strings.pop()?)?),
+            _ => return None
+        };
         let mut indices = Vec::new();
         for index_string in index_strings {
             let index = parse_chess_move(index_string)?;
@@ -21,11 +30,33 @@ impl ChessMove {
                 indices.push(index);
             }
         }
-        let start_x = indices.get(0)?;
+        let start_x = indices.first()?;
         let start_y = indices.get(1)?;
         let end_x = indices.get(2)?;
         let end_y = indices.get(3)?;
-        Some(ChessMove {start_pos: (*start_x, *start_y), end_pos: (*end_x, *end_y)})
+        Some(ChessMove {start_pos: (*start_x, *start_y), end_pos: (*end_x, *end_y), promotion})
+    }
+
+    //Long algebraic notation as spoken by UCI, e.g. "e2e4" or "e7e8q"
+    pub fn from_uci(uci_string: &str) -> Option<ChessMove> {
+        if !uci_string.is_ascii() || (uci_string.len() != 4 && uci_string.len() != 5) {
+            return None;
+        }
+        let start_pos = parse_square(&uci_string[0..2])?;
+        let end_pos = parse_square(&uci_string[2..4])?;
+        let promotion = match uci_string.get(4..) {
+            Some("") | None => None,
+            Some(piece_string) => Some(parse_promotion(piece_string)?)
+        };
+        Some(ChessMove {start_pos, end_pos, promotion})
+    }
+
+    pub fn to_uci(&self) -> String {
+        let mut uci_string = square_name(self.start_pos) + &square_name(self.end_pos);
+        if let Some(piece) = self.promotion {
+            uci_string.push(piece.to_char());
+        }
+        uci_string
     }
 
     pub fn get_start_x(&self) -> usize {
@@ -43,11 +74,16 @@ impl ChessMove {
 }
 
 fn parse_chess_move(move_string: &str) -> Option<usize> {
-    let move_usize = move_string.parse::<usize>();
-    match move_usize {
-        Ok(move_usize) => Some(move_usize),
-        _ => None
+    move_string.parse::<usize>().ok()
+}
+
+fn parse_promotion(piece_string: &str) -> Option<Piece> {
+    let mut chars = piece_string.chars();
+    let piece = Piece::from_char(chars.next()?)?;
+    if chars.next().is_some() || !piece.is_promotion_piece() {
+        return None;
     }
+    Some(piece)
 }
 
 fn valid_index(index: usize) -> bool {
@@ -56,7 +92,11 @@ fn valid_index(index: usize) -> bool {
 
 impl fmt::Display for ChessMove {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} {} to {} {}", self.get_start_x(), self.get_start_y(), self.get_end_x(), self.get_end_y())
+        write!(f, "{} {} to {} {}", self.get_start_x(), self.get_start_y(), self.get_end_x(), self.get_end_y())?;
+        if let Some(piece) = self.promotion {
+            write!(f, " promoting to {:?}", piece)?;
+        }
+        Ok(())
     }
 }
 
@@ -103,4 +143,53 @@ fn too_small_index(){
 fn empty_string(){
     let move_string = String::from("");
     ChessMove::from(move_string).unwrap();
-}
\ No newline at end of file
+}
+
+#[test]
+fn promotion_move(){
+    let move_string = String::from("4 6 4 7 q");
+    let chess_move = ChessMove::from(move_string).unwrap();
+    assert_eq!(chess_move.end_pos, (4, 7));
+    assert_eq!(chess_move.promotion, Some(Piece::Queen));
+    let move_string = String::from("4 6 3 7 N");
+    let chess_move = ChessMove::from(move_string).unwrap();
+    assert_eq!(chess_move.promotion, Some(Piece::Knight));
+}
+
+#[test]
+#[should_panic]
+fn promotion_to_king(){
+    let move_string = String::from("4 6 4 7 k");
+    ChessMove::from(move_string).unwrap();
+}
+
+#[test]
+#[should_panic]
+fn promotion_suffix_too_long(){
+    let move_string = String::from("4 6 4 7 qq");
+    ChessMove::from(move_string).unwrap();
+}
+
+#[test]
+fn uci_move(){
+    let chess_move = ChessMove::from_uci("e2e4").unwrap();
+    assert_eq!(chess_move, ChessMove::from(String::from("4 1 4 3")).unwrap());
+    assert_eq!("e2e4", chess_move.to_uci());
+    assert_eq!("a1h8", ChessMove::new((0, 0), (7, 7)).to_uci());
+}
+
+#[test]
+fn uci_promotion(){
+    let chess_move = ChessMove::from_uci("e7e8q").unwrap();
+    assert_eq!(Some(Piece::Queen), chess_move.promotion);
+    assert_eq!("e7e8q", chess_move.to_uci());
+    assert_eq!("b2a1n", ChessMove{start_pos: (1, 1), end_pos: (0, 0), promotion: Some(Piece::Knight)}.to_uci());
+}
+
+#[test]
+fn invalid_uci_moves(){
+    for uci_string in ["", "e2", "e2e", "e2e9", "i2e4", "e7e8k", "e7e8qq", "e2-e4", "é2e4"] {
+        assert_eq!(None, ChessMove::from_uci(uci_string));
+    }
+}
+
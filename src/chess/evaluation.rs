@@ -2,6 +2,7 @@ use crate::chess::tile::Tile;
 use crate::chess::piece::Piece;
 use crate::chess::color::Color;
 use crate::chess::board::Board;
+use crate::chess::bitboard::PIECES;
 
 use super::game_result::GameResult;
 
@@ -10,10 +11,10 @@ pub fn evaluate(board: &Board) -> i32 {
         return get_score_for_decisive_board(board);
     }
     let mut score = 0;
-    for column in &board.tiles {
-        for tile in column {
-            score += evaluate_piece_value(tile);
-        }
+    for piece in PIECES {
+        let white_pieces = board.pieces(Color::White, piece).count_ones() as i32;
+        let black_pieces = board.pieces(Color::Black, piece).count_ones() as i32;
+        score += evaluate_piece_value(&Tile::new(Color::White, piece)) * (white_pieces - black_pieces);
     }
     score
 }
@@ -24,22 +25,27 @@ fn decisive_board(board: &Board) -> bool {
 
 fn get_score_for_decisive_board(board: &Board) -> i32 {
     match board.result() {
-        GameResult::WhiteWin => 200,
-        GameResult::BlackWin => -200,
-        GameResult::Draw => 0,
+        GameResult::WhiteWin(_) => 200,
+        GameResult::BlackWin(_) => -200,
+        GameResult::Draw(_) => 0,
         _ => 0
     }
 }
 
-fn evaluate_piece_value(tile: &Tile) -> i32{
-    let value = match tile.piece {
+//Material value in pawns, the king has none since it can't be traded
+pub fn piece_value(piece: Piece) -> i32 {
+    match piece {
         Piece::Pawn => 1,
         Piece::Rook => 5,
         Piece::Bishop => 3,
         Piece::Knight => 3,
         Piece::Queen => 9,
         _ => 0
-    };
+    }
+}
+
+fn evaluate_piece_value(tile: &Tile) -> i32{
+    let value = piece_value(tile.piece);
     if tile.color == Color::Black {
         return -value;
     }
@@ -49,49 +55,49 @@ fn evaluate_piece_value(tile: &Tile) -> i32{
 #[test]
 fn white_win(){
     let mut board = Board::empty();
-    board.tiles[0][0] = Tile{piece: Piece::King, color: Color::Black};
-    board.tiles[0][2] = Tile{piece: Piece::King, color: Color::White};
-    board.tiles[2][1] = Tile{piece: Piece::Queen, color: Color::White};
+    board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(0, 2, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(2, 1, Tile{piece: Piece::Queen, color: Color::White});
     board = board.make_move(2, 1, 0, 1);
     assert!(evaluate(&board) > 50);
 }
 #[test]
 fn white_advantage(){
     let mut board = Board::empty();
-    board.tiles[0][0] = Tile{piece: Piece::King, color: Color::White};
-    board.tiles[0][5] = Tile{piece: Piece::King, color: Color::Black};
-    board.tiles[0][7] = Tile{piece: Piece::Queen, color: Color::White};
+    board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(0, 5, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(0, 7, Tile{piece: Piece::Queen, color: Color::White});
     assert!(evaluate(&board) > 0);
 }
 #[test]
 fn black_win(){
     let mut board = Board::empty();
-    board.tiles[0][0] = Tile{piece: Piece::King, color: Color::White};
-    board.tiles[0][2] = Tile{piece: Piece::King, color: Color::Black};
-    board.tiles[0][1] = Tile{piece: Piece::Queen, color: Color::Black};
+    board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(0, 2, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(0, 1, Tile{piece: Piece::Queen, color: Color::Black});
     assert!(evaluate(&board) < 50);
 }
 #[test]
 fn black_advantage(){
     let mut board = Board::empty();
-    board.tiles[0][0] = Tile{piece: Piece::King, color: Color::White};
-    board.tiles[0][5] = Tile{piece: Piece::King, color: Color::Black};
-    board.tiles[0][7] = Tile{piece: Piece::Queen, color: Color::Black};
+    board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(0, 5, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(0, 7, Tile{piece: Piece::Queen, color: Color::Black});
     assert!(evaluate(&board) < 0);
 }
 #[test]
 fn stalemate(){
     let mut board = Board::empty();
-    board.tiles[0][0] = Tile{piece: Piece::King, color: Color::White};
-    board.tiles[0][2] = Tile{piece: Piece::King, color: Color::Black};
-    board.tiles[2][1] = Tile{piece: Piece::Queen, color: Color::Black};
+    board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(0, 2, Tile{piece: Piece::King, color: Color::Black});
+    board.set_tile(2, 1, Tile{piece: Piece::Queen, color: Color::Black});
     assert_eq!(0, evaluate(&board));
 }
 #[test]
 fn dead_draw(){
     let mut board = Board::empty();
-    board.tiles[0][0] = Tile{piece: Piece::King, color: Color::White};
-    board.tiles[0][5] = Tile{piece: Piece::King, color: Color::Black};
+    board.set_tile(0, 0, Tile{piece: Piece::King, color: Color::White});
+    board.set_tile(0, 5, Tile{piece: Piece::King, color: Color::Black});
     assert_eq!(0, evaluate(&board));
 }
 
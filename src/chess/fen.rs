@@ -0,0 +1,218 @@
+use super::{bitboard::square_index, castling_rights::CastlingRights, color::Color, piece::Piece, square::parse_square, tile::Tile};
+use std::fmt;
+
+pub const STARTING_POSITION: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenError {
+    WrongNumberOfFields(usize),
+    InvalidPiecePlacement(String),
+    InvalidKingCount,
+    InvalidSideToMove(String),
+    InvalidCastlingRights(String),
+    InvalidEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String)
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::WrongNumberOfFields(count) => write!(f, "expected 4 or 6 fields, got {}", count),
+            FenError::InvalidPiecePlacement(field) => write!(f, "invalid piece placement '{}'", field),
+            FenError::InvalidKingCount => write!(f, "each side needs exactly one king"),
+            FenError::InvalidSideToMove(field) => write!(f, "invalid side to move '{}'", field),
+            FenError::InvalidCastlingRights(field) => write!(f, "invalid castling rights '{}'", field),
+            FenError::InvalidEnPassant(field) => write!(f, "invalid en passant square '{}'", field),
+            FenError::InvalidHalfmoveClock(field) => write!(f, "invalid halfmove clock '{}'", field),
+            FenError::InvalidFullmoveNumber(field) => write!(f, "invalid fullmove number '{}'", field)
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+//Ranks are listed from 8 down to 1, files from a to h, the tiles come back indexed by square
+pub(crate) fn parse_piece_placement(field: &str) -> Result<[Tile; 64], FenError> {
+    let error = || FenError::InvalidPiecePlacement(field.to_string());
+    let mut tiles = [Tile::new(Color::Empty, Piece::Empty); 64];
+    let ranks: Vec<&str> = field.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(error());
+    }
+    for (rank_index, rank) in ranks.iter().enumerate() {
+        let y = 7 - rank_index;
+        let mut x = 0;
+        for piece_char in rank.chars() {
+            if let Some(empty_squares) = piece_char.to_digit(10) {
+                if empty_squares == 0 || empty_squares > 8 {
+                    return Err(error());
+                }
+                x += empty_squares as usize;
+            } else {
+                let piece = Piece::from_char(piece_char).ok_or_else(error)?;
+                let color = if piece_char.is_ascii_uppercase() {Color::White} else {Color::Black};
+                if x > 7 || (piece == Piece::Pawn && (y == 0 || y == 7)) {
+                    return Err(error());
+                }
+                tiles[square_index(x, y)] = Tile::new(color, piece);
+                x += 1;
+            }
+        }
+        if x != 8 {
+            return Err(error());
+        }
+    }
+    for color in [Color::White, Color::Black] {
+        let kings = tiles.iter().filter(|tile| tile.piece == Piece::King && tile.color == color).count();
+        if kings != 1 {
+            return Err(FenError::InvalidKingCount);
+        }
+    }
+    Ok(tiles)
+}
+
+pub(crate) fn piece_placement(tiles: &[Tile; 64]) -> String {
+    let mut ranks = Vec::<String>::new();
+    for y in (0..8).rev() {
+        let mut rank = String::new();
+        let mut empty_squares = 0;
+        for x in 0..8 {
+            let tile = tiles[square_index(x, y)];
+            if tile.piece == Piece::Empty {
+                empty_squares += 1;
+                continue;
+            }
+            if empty_squares > 0 {
+                rank.push_str(&empty_squares.to_string());
+                empty_squares = 0;
+            }
+            rank.push(tile_char(&tile));
+        }
+        if empty_squares > 0 {
+            rank.push_str(&empty_squares.to_string());
+        }
+        ranks.push(rank);
+    }
+    ranks.join("/")
+}
+
+fn tile_char(tile: &Tile) -> char {
+    match tile.color {
+        Color::White => tile.piece.to_char().to_ascii_uppercase(),
+        _ => tile.piece.to_char()
+    }
+}
+
+pub(crate) fn parse_side_to_move(field: &str) -> Result<Color, FenError> {
+    match field {
+        "w" => Ok(Color::White),
+        "b" => Ok(Color::Black),
+        _ => Err(FenError::InvalidSideToMove(field.to_string()))
+    }
+}
+
+pub(crate) fn side_to_move(color: Color) -> &'static str {
+    match color {
+        Color::Black => "b",
+        _ => "w"
+    }
+}
+
+pub(crate) fn parse_castling_rights(field: &str) -> Result<CastlingRights, FenError> {
+    let mut castling_rights = CastlingRights::none();
+    if field == "-" {
+        return Ok(castling_rights);
+    }
+    if field.is_empty() {
+        return Err(FenError::InvalidCastlingRights(field.to_string()));
+    }
+    for right in field.chars() {
+        let flag = match right {
+            'K' => &mut castling_rights.white_king_side,
+            'Q' => &mut castling_rights.white_queen_side,
+            'k' => &mut castling_rights.black_king_side,
+            'q' => &mut castling_rights.black_queen_side,
+            _ => return Err(FenError::InvalidCastlingRights(field.to_string()))
+        };
+        if *flag {
+            return Err(FenError::InvalidCastlingRights(field.to_string()));
+        }
+        *flag = true;
+    }
+    Ok(castling_rights)
+}
+
+pub(crate) fn castling_rights(castling_rights: &CastlingRights) -> String {
+    let mut field = String::new();
+    if castling_rights.white_king_side {
+        field.push('K');
+    }
+    if castling_rights.white_queen_side {
+        field.push('Q');
+    }
+    if castling_rights.black_king_side {
+        field.push('k');
+    }
+    if castling_rights.black_queen_side {
+        field.push('q');
+    }
+    if field.is_empty() {
+        field.push('-');
+    }
+    field
+}
+
+//The target square must be on the third rank after a white double push or the sixth after a black one
+pub(crate) fn parse_en_passant(field: &str, player_to_move: Color) -> Result<Option<(usize, usize)>, FenError> {
+    if field == "-" {
+        return Ok(None);
+    }
+    let expected_rank = if player_to_move == Color::White {5} else {2};
+    match parse_square(field) {
+        Some((x, y)) if y == expected_rank => Ok(Some((x, y))),
+        _ => Err(FenError::InvalidEnPassant(field.to_string()))
+    }
+}
+
+pub(crate) fn parse_halfmove_clock(field: &str) -> Result<u32, FenError> {
+    field.parse::<u32>().map_err(|_| FenError::InvalidHalfmoveClock(field.to_string()))
+}
+
+pub(crate) fn parse_fullmove_number(field: &str) -> Result<u32, FenError> {
+    match field.parse::<u32>() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => Err(FenError::InvalidFullmoveNumber(field.to_string()))
+    }
+}
+
+#[test]
+fn castling_rights_round_trip() {
+    for field in ["KQkq", "Kq", "-", "Q", "k"] {
+        assert_eq!(field, castling_rights(&parse_castling_rights(field).unwrap()));
+    }
+}
+
+#[test]
+fn invalid_castling_rights() {
+    assert!(parse_castling_rights("KK").is_err());
+    assert!(parse_castling_rights("X").is_err());
+    assert!(parse_castling_rights("").is_err());
+}
+
+#[test]
+fn en_passant_on_wrong_rank() {
+    assert!(parse_en_passant("e3", Color::White).is_err());
+    assert_eq!(Ok(Some((4, 2))), parse_en_passant("e3", Color::Black));
+    assert_eq!(Ok(None), parse_en_passant("-", Color::Black));
+}
+
+#[test]
+fn invalid_piece_placement() {
+    assert!(parse_piece_placement("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP").is_err());
+    assert!(parse_piece_placement("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR").is_err());
+    assert!(parse_piece_placement("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR").is_err());
+    assert!(parse_piece_placement("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX").is_err());
+    assert!(parse_piece_placement("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1BNR").is_err());
+    assert!(parse_piece_placement("rnbqkbnP/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR").is_err());
+}
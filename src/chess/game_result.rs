@@ -1,7 +1,97 @@
-#[derive(Debug, PartialEq)]
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum GameResult {
-    WhiteWin,
-    BlackWin,
-    Draw,
+    WhiteWin(Termination),
+    BlackWin(Termination),
+    Draw(Termination),
     Ongoing
-    }
\ No newline at end of file
+    }
+
+impl GameResult {
+    pub fn termination(&self) -> Option<Termination> {
+        match self {
+            GameResult::WhiteWin(termination) | GameResult::BlackWin(termination) | GameResult::Draw(termination) => Some(*termination),
+            GameResult::Ongoing => None
+        }
+    }
+
+    //The value of the PGN Result tag, also used as the game termination marker after the moves
+    pub fn pgn_result(&self) -> &'static str {
+        match self {
+            GameResult::WhiteWin(_) => "1-0",
+            GameResult::BlackWin(_) => "0-1",
+            GameResult::Draw(_) => "1/2-1/2",
+            GameResult::Ongoing => "*"
+        }
+    }
+
+    //The Result and Termination tags, an ongoing game has no Termination tag
+    pub fn pgn_tags(&self) -> String {
+        match self.termination() {
+            Some(termination) => format!("[Result \"{}\"]\n[Termination \"{}\"]\n", self.pgn_result(), termination),
+            None => format!("[Result \"{}\"]\n", self.pgn_result())
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameResult::WhiteWin(termination) => write!(f, "1-0, White wins by {}", termination),
+            GameResult::BlackWin(termination) => write!(f, "0-1, Black wins by {}", termination),
+            GameResult::Draw(termination) => write!(f, "1/2-1/2, draw by {}", termination),
+            GameResult::Ongoing => write!(f, "*, game in progress")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Termination {
+    Checkmate,
+    Resignation,
+    Timeout,
+    Stalemate,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    FiftyMoveRule,
+    SeventyFiveMoveRule,
+    InsufficientMaterial,
+    Agreement
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Resignation => "resignation",
+            Termination::Timeout => "timeout",
+            Termination::Stalemate => "stalemate",
+            Termination::ThreefoldRepetition => "threefold repetition",
+            Termination::FivefoldRepetition => "fivefold repetition",
+            Termination::FiftyMoveRule => "fifty-move rule",
+            Termination::SeventyFiveMoveRule => "seventy-five-move rule",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::Agreement => "agreement"
+        };
+        write!(f, "{}", description)
+    }
+}
+
+//Decides whether threefold repetition and the fifty move rule end the game by themselves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrawRule {
+    //Draws are claimed as soon as they can be, which is what an engine playing for itself does
+    Claimed,
+    //Only fivefold repetition and the seventy-five move rule end the game, the claimable draws
+    //are left to Board::claimable_draw
+    Automatic
+}
+
+#[test]
+fn pgn_tags() {
+    assert_eq!("[Result \"1-0\"]\n[Termination \"checkmate\"]\n", GameResult::WhiteWin(Termination::Checkmate).pgn_tags());
+    assert_eq!("[Result \"1/2-1/2\"]\n[Termination \"threefold repetition\"]\n", GameResult::Draw(Termination::ThreefoldRepetition).pgn_tags());
+    assert_eq!("[Result \"*\"]\n", GameResult::Ongoing.pgn_tags());
+    assert_eq!("0-1, Black wins by timeout", GameResult::BlackWin(Termination::Timeout).to_string());
+}
@@ -1,8 +1,17 @@
 pub mod board;
+pub mod bitboard;
+#[cfg(not(feature = "classical_attacks"))]
+mod magic;
 pub mod color;
 pub mod chess_move;
-pub(crate) mod piece;
-pub(crate) mod tile;
+pub mod piece;
+pub mod tile;
 pub mod game_result;
+pub mod castling_rights;
+pub mod fen;
+pub mod san;
+pub mod perft;
+pub(crate) mod square;
 mod move_generator;
+mod zobrist;
 pub mod evaluation;
\ No newline at end of file
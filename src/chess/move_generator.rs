@@ -1,4 +1,4 @@
-use super::{board::Board, chess_move::ChessMove, color::Color, piece::Piece};
+use super::{bitboard::{self, Bitboard, between, square_bit, square_coordinates, square_index, squares}, board::Board, chess_move::ChessMove, color::Color, piece::Piece};
 
 
 
@@ -8,199 +8,167 @@ pub struct MoveGenerator<'a> {
 
 impl MoveGenerator<'_> {
 
-    pub fn new(board: &Board) -> MoveGenerator{
+    pub fn new(board: &Board) -> MoveGenerator<'_>{
         MoveGenerator {board}
     }
 
+    //Moves that follow the piece rules for the player to move, some may still leave the own king in check
     pub fn possible_moves(&self) -> Vec<ChessMove> {
         let mut possible_moves = Vec::<ChessMove>::new();
-        for (start_x, start_y) in self.board.all_squares() {
-            let piece = self.board.tiles[start_x][start_y].piece;
-            for (end_x, end_y) in piece.squares_that_fit_move_pattern(start_x, start_y) {
-                if self.piece_can_reach(start_x, start_y, end_x, end_y) {
-                    possible_moves.push(ChessMove{start_pos: (start_x, start_y), end_pos : (end_x, end_y)});
+        for start in squares(self.board.occupancy(self.board.player_to_move())) {
+            let start_pos = square_coordinates(start);
+            let piece = self.board.tile(start_pos.0, start_pos.1).piece;
+            for end in squares(self.targets(start)) {
+                let end_pos = square_coordinates(end);
+                if piece == Piece::Pawn && (end_pos.1 == 0 || end_pos.1 == 7) {
+                    for promotion in [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
+                        possible_moves.push(ChessMove{start_pos, end_pos, promotion: Some(promotion)});
+                    }
+                } else {
+                    possible_moves.push(ChessMove::new(start_pos, end_pos));
                 }
             }
         }
+        possible_moves.append(&mut self.castling_moves());
         possible_moves
     }
-    pub fn piece_can_reach(&self, start_x: usize,start_y: usize, end_x: usize, end_y: usize) -> bool {
-        if start_x == end_x && start_y == end_y {
-            return false
-        }
-        let tile = self.board.tiles[start_x][start_y];
-        match tile.piece {
-            Piece::Pawn => self.can_pawn_reach(start_x, start_y, end_x, end_y),
-            Piece::Rook => self.can_rook_reach(start_x, start_y, end_x, end_y),
-            Piece::Knight => self.can_knight_reach(start_x, start_y, end_x, end_y),
-            Piece::Bishop => self.can_bishop_reach(start_x, start_y, end_x, end_y),
-            Piece::Queen => self.can_queen_reach(start_x, start_y, end_x, end_y),
-            Piece::King => self.can_king_reach(start_x, start_y, end_x, end_y),
-            _ => false
-        }
-    }
-
-    fn can_pawn_reach(&self, start_x: usize,start_y: usize, end_x: usize, end_y: usize) -> bool {
-        let color = self.board.tiles[start_x][start_y].color;
-        let y_difference = end_y as i128 - start_y as i128;
-        let x_difference = end_x as i128 - start_x as i128;
-
-        match color {
-            Color::White => {
-                //Captures
-                if x_difference.abs() == 1 
-                && y_difference == 1 
-                && Color::opposing_color(self.board.tiles[start_x][start_y].color) == self.board.tiles[end_x][end_y].color {
-                    return true;
-                } else if self.unobstructed_file(start_x, start_y, end_x, end_y) {
-                    //Can only move two steps if on start square
-                return (y_difference == 2 && start_y == 1) || y_difference == 1;
-                } 
-                false
-            //En passant is fake news
-            },
-            Color::Black => {
-                if x_difference.abs() == 1 
-                && y_difference == -1 
-                && Color::opposing_color(self.board.tiles[start_x][start_y].color) == self.board.tiles[end_x][end_y].color {
-                    return true;
-                } else if self.unobstructed_file(start_x, start_y, end_x, end_y) {
-                return (y_difference == -2 && start_y == 6) || y_difference == -1;
-                } 
-                false
-            },
-            _ => false
-        }
-    }
-
-    fn can_rook_reach(&self, start_x: usize,start_y: usize, end_x: usize, end_y: usize) -> bool {
-        if start_x == end_x {
-            let end_index = if start_y < end_y {end_y - 1} else {end_y + 1};
-            return self.unobstructed_file(start_x, start_y, end_x, end_index) && self.board.tiles[start_x][start_y].color != self.board.tiles[end_x][end_y].color
-        } else if start_y == end_y {
-            let end_index = if start_x < end_x {end_x - 1} else {end_x + 1};
-            return  self.unobstructed_rank(start_x, start_y, end_index, end_y) && self.board.tiles[start_x][start_y].color != self.board.tiles[end_x][end_y].color
-        }
-        false
-    }
 
-    fn can_bishop_reach(&self, start_x: usize,start_y: usize, end_x: usize, end_y: usize) -> bool {
-        self.unobstructed_diagonal(start_x, start_y, end_x, end_y) && self.board.tiles[start_x][start_y].color != self.board.tiles[end_x][end_y].color
+    fn castling_moves(&self) -> Vec<ChessMove> {
+        let color = self.board.player_to_move();
+        let home_rank = if color == Color::White {0} else {7};
+        vec!(6, 2).into_iter()
+        .filter(|end_x| self.can_castle(4, home_rank, *end_x))
+        .map(|end_x| ChessMove::new((4, home_rank), (end_x, home_rank)))
+        .collect()
     }
 
-    fn can_queen_reach(&self, start_x: usize,start_y: usize, end_x: usize, end_y: usize) -> bool {
-        self.can_bishop_reach(start_x, start_y, end_x, end_y) || self.can_rook_reach(start_x, start_y, end_x, end_y)
+    pub fn square_attacked(&self, x: usize, y: usize, attacking_color: Color) -> bool {
+        self.attackers(square_index(x, y), attacking_color, self.board.occupied(), 0) != 0
     }
 
-    fn can_king_reach(&self, start_x: usize,start_y: usize, end_x: usize, end_y: usize) -> bool {
-        let y_difference = end_y as i128 - start_y as i128;
-        let x_difference = end_x as i128 - start_x as i128;
-        if x_difference.abs() > 1 || y_difference.abs() > 1 {
-            return false;
+    //Plays the move on the occupancy alone, which is all the check test needs, instead of copying the board
+    pub fn leaves_king_in_check(&self, chess_move: &ChessMove) -> bool {
+        let (start_x, start_y) = chess_move.start_pos;
+        let (end_x, end_y) = chess_move.end_pos;
+        let (start, end) = (square_index(start_x, start_y), square_index(end_x, end_y));
+        let tile = self.board.tile(start_x, start_y);
+        let mut captured = square_bit(end);
+        if tile.piece == Piece::Pawn && start_x != end_x && self.board.tile(end_x, end_y).piece == Piece::Empty {
+            captured = square_bit(square_index(end_x, start_y));
         }
-        self.board.tiles[start_x][start_y].color != self.board.tiles[end_x][end_y].color
+        let mut occupied = (self.board.occupied() & !square_bit(start) & !captured) | square_bit(end);
+        if tile.piece == Piece::King && (end_x as i128 - start_x as i128).abs() == 2 {
+            let (rook_start_x, rook_end_x) = if end_x == 6 {(7, 5)} else {(0, 3)};
+            occupied ^= square_bit(square_index(rook_start_x, start_y)) | square_bit(square_index(rook_end_x, start_y));
+        }
+        let king = match tile.piece {
+            Piece::King => end,
+            _ => match squares(self.board.pieces(tile.color, Piece::King)).next() {
+                Some(king) => king,
+                None => return false
+            }
+        };
+        self.attackers(king, Color::opposing_color(tile.color), occupied, captured) != 0
     }
 
-    fn can_knight_reach(&self, start_x: usize,start_y: usize, end_x: usize, end_y: usize) -> bool {
-        let y_difference = end_y as i128 - start_y as i128;
-        let x_difference = end_x as i128 - start_x as i128;
-        self.fits_knight_pattern(x_difference, y_difference) && self.board.tiles[start_x][start_y].color != self.board.tiles[end_x][end_y].color
+    //Pawns only attack diagonally and castling never attacks anything, so the attack tables are enough
+    fn attackers(&self, square: usize, attacking_color: Color, occupied: Bitboard, captured: Bitboard) -> Bitboard {
+        let pieces = |piece: Piece| self.board.pieces(attacking_color, piece) & !captured;
+        let diagonal_sliders = pieces(Piece::Bishop) | pieces(Piece::Queen);
+        let straight_sliders = pieces(Piece::Rook) | pieces(Piece::Queen);
+        (bitboard::pawn_attacks(Color::opposing_color(attacking_color), square) & pieces(Piece::Pawn))
+            | (bitboard::knight_attacks(square) & pieces(Piece::Knight))
+            | (bitboard::king_attacks(square) & pieces(Piece::King))
+            | (bitboard::bishop_attacks(square, occupied) & diagonal_sliders)
+            | (bitboard::rook_attacks(square, occupied) & straight_sliders)
     }
 
-    fn fits_knight_pattern(&self, x_difference: i128, y_difference: i128) -> bool {
-        (x_difference.abs() == 2 && y_difference.abs() == 1) || (x_difference.abs() == 1 && y_difference.abs() == 2)
+    pub fn piece_can_reach(&self, start_x: usize,start_y: usize, end_x: usize, end_y: usize) -> bool {
+        if start_x == end_x && start_y == end_y {
+            return false
+        }
+        let tile = self.board.tile(start_x, start_y);
+        if tile.piece == Piece::King && (end_x as i128 - start_x as i128).abs() == 2 && start_y == end_y {
+            return self.can_castle(start_x, start_y, end_x);
+        }
+        self.targets(square_index(start_x, start_y)) & square_bit(square_index(end_x, end_y)) != 0
     }
 
-    fn unobstructed_file(&self, start_x: usize, start_y: usize, end_x: usize, end_y: usize) -> bool {
-        if start_x != end_x {
-            return false;
+    //Every square the piece on the start square can move to, apart from castling
+    fn targets(&self, start: usize) -> Bitboard {
+        let (start_x, start_y) = square_coordinates(start);
+        let tile = self.board.tile(start_x, start_y);
+        if tile.color == Color::Empty {
+            return 0;
         }
-        if start_y < end_y {
-            ((start_y + 1)..(end_y + 1)).all(|idx| self.board.tiles[start_x][idx].piece == Piece::Empty)
-        } else {
-            (end_y..start_y).all(|idx| self.board.tiles[start_x][idx].piece == Piece::Empty)
+        let occupied = self.board.occupied();
+        let not_own = !self.board.occupancy(tile.color);
+        match tile.piece {
+            Piece::Pawn => self.pawn_targets(start, tile.color),
+            Piece::Knight => bitboard::knight_attacks(start) & not_own,
+            Piece::Bishop => bitboard::bishop_attacks(start, occupied) & not_own,
+            Piece::Rook => bitboard::rook_attacks(start, occupied) & not_own,
+            Piece::Queen => bitboard::queen_attacks(start, occupied) & not_own,
+            Piece::King => bitboard::king_attacks(start) & not_own,
+            Piece::Empty => 0
         }
     }
 
-    fn unobstructed_rank(&self, start_x: usize, start_y: usize, end_x: usize, end_y: usize) -> bool {
-        if start_y != end_y {
-            return false;
+    fn pawn_targets(&self, start: usize, color: Color) -> Bitboard {
+        let (start_x, start_y) = square_coordinates(start);
+        let (forward, home_rank, en_passant_rank) = match color {
+            Color::White => (1, 1, 5),
+            _ => (-1, 6, 2)
+        };
+        //The en passant square is behind the enemy pawn, on the sixth rank seen from the capturing side
+        let mut capturable = self.board.occupancy(Color::opposing_color(color));
+        if let Some((x, y)) = self.board.en_passant().filter(|(_x, y)| *y == en_passant_rank) {
+            capturable |= square_bit(square_index(x, y));
+        }
+        let mut targets = bitboard::pawn_attacks(color, start) & capturable;
+        let one_step_y = start_y as i32 + forward;
+        if !(0..8).contains(&one_step_y) {
+            return targets;
         }
-        if start_x < end_x {
-            ((start_x + 1)..(end_x + 1)).all(|idx| self.board.tiles[idx][start_y].piece == Piece::Empty)
-        } else {
-            (end_x..start_x).all(|idx| self.board.tiles[idx][start_y].piece == Piece::Empty)
+        let one_step = square_index(start_x, one_step_y as usize);
+        if self.board.occupied() & square_bit(one_step) == 0 {
+            targets |= square_bit(one_step);
+            //Can only move two steps if on start square
+            if start_y == home_rank {
+                let two_steps = square_index(start_x, (one_step_y + forward) as usize);
+                if self.board.occupied() & square_bit(two_steps) == 0 {
+                    targets |= square_bit(two_steps);
+                }
+            }
         }
+        targets
     }
 
-    fn unobstructed_diagonal(&self, start_x: usize, start_y: usize, end_x: usize, end_y: usize) -> bool {
-        let y_difference = end_y as i128 - start_y as i128;
-        let x_difference = end_x as i128 - start_x as i128;
-        if x_difference.abs() != y_difference.abs() || x_difference == 0 || y_difference == 0 {
+    //Checks everything but the king ending up in check, which the legality filter in Board handles
+    fn can_castle(&self, start_x: usize, start_y: usize, end_x: usize) -> bool {
+        let color = self.board.tile(start_x, start_y).color;
+        let home_rank = match color {
+            Color::White => 0,
+            Color::Black => 7,
+            _ => return false
+        };
+        if start_x != 4 || start_y != home_rank {
             return false;
         }
-        let x_order = x_difference / x_difference.abs();
-        let y_order = y_difference / y_difference.abs();
-        //Add the direction we're going in so we don't check if the start square is empty
-        let mut x_idx = start_x as i128 + x_order;
-        let mut y_idx = start_y as i128 + y_order;
-        while x_idx != end_x as i128 && y_idx != end_y as i128 {
-            if self.board.tiles[x_idx as usize][y_idx as usize].piece != Piece::Empty {
-                return false;
-            }
-            x_idx += x_order;
-            y_idx += y_order;
+        let castling_rights = self.board.castling_rights();
+        let (has_right, rook_x, passed_x) = match end_x {
+            6 => (castling_rights.king_side(color), 7, 5),
+            2 => (castling_rights.queen_side(color), 0, 3),
+            _ => return false
+        };
+        let rook = self.board.tile(rook_x, home_rank);
+        if !has_right || rook.piece != Piece::Rook || rook.color != color {
+            return false;
         }
-        true
+        if between(square_index(start_x, home_rank), square_index(rook_x, home_rank)) & self.board.occupied() != 0 {
+            return false;
+        }
+        let opposing_color = Color::opposing_color(color);
+        !self.square_attacked(start_x, home_rank, opposing_color) && !self.square_attacked(passed_x, home_rank, opposing_color)
     }
 }
-
-
-#[test]
-fn unobstructed_file_all_empty(){
-    let board = Board::new();
-    let move_generator = MoveGenerator::new(&board);
-    assert!(move_generator.unobstructed_file(4, 2, 4, 5));
-}
-
-#[test]
-fn unobstructed_file_one_step(){
-    let board = Board::new();
-    let move_generator = MoveGenerator::new(&board);
-    assert!(move_generator.unobstructed_file(4, 4, 4, 5));
-}
-
-#[test]
-fn unobstructed_file_start_with_piece(){
-    let board = Board::new();
-    let move_generator = MoveGenerator::new(&board);
-    assert!(move_generator.unobstructed_file(4, 1, 4, 5));
-}
-
-#[test]
-fn obstructed_file(){
-    let board = Board::new();
-    let move_generator = MoveGenerator::new(&board);
-    assert!(!move_generator.unobstructed_file(4, 1, 4, 6));
-}
-
-#[test]
-fn obstructed_file_one_step(){
-    let board = Board::new();
-    let move_generator = MoveGenerator::new(&board);
-    assert!(!move_generator.unobstructed_file(4, 5, 4, 6));
-}
-
-#[test]
-fn unobstructed_file_start_with_piece_start_larger_than_end(){
-    let board = Board::new();
-    let move_generator = MoveGenerator::new(&board);
-    assert!(move_generator.unobstructed_file(4, 6, 4, 2));
-}
-
-#[test]
-fn obstructed_file_start_with_piece_start_larger_than_end(){
-    let board = Board::new();
-    let move_generator = MoveGenerator::new(&board);
-    assert!(!move_generator.unobstructed_file(4, 6, 4, 1));
-}
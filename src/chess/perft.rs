@@ -0,0 +1,59 @@
+use super::{board::Board, chess_move::ChessMove};
+
+impl Board {
+    //Counts the leaf nodes of the legal move tree, the standard way of checking a move generator
+    pub fn perft(&self, depth: u32) -> u64 {
+        perft(&mut self.clone(), depth)
+    }
+
+    //The perft count below each legal move, for finding which move a wrong total comes from
+    pub fn divide(&self, depth: u32) -> Vec<(ChessMove, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+        let mut board = self.clone();
+        board.legal_moves().into_iter()
+        .map(|chess_move| {
+            let undo_info = board.make(chess_move);
+            let nodes = perft(&mut board, depth - 1);
+            board.unmake(chess_move, undo_info);
+            (chess_move, nodes)
+        })
+        .collect()
+    }
+}
+
+fn perft(board: &mut Board, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let chess_moves = board.legal_moves();
+    //The moves at the last ply don't need to be played to be counted
+    if depth == 1 {
+        return chess_moves.len() as u64;
+    }
+    let mut nodes = 0;
+    for chess_move in chess_moves {
+        let undo_info = board.make(chess_move);
+        nodes += perft(board, depth - 1);
+        board.unmake(chess_move, undo_info);
+    }
+    nodes
+}
+
+#[test]
+fn start_position() {
+    let board = Board::new();
+    assert_eq!(1, board.perft(0));
+    assert_eq!(20, board.perft(1));
+    assert_eq!(400, board.perft(2));
+}
+
+#[test]
+fn divide_sums_to_perft() {
+    let board = Board::new();
+    let divide = board.divide(3);
+    assert_eq!(20, divide.len());
+    assert_eq!(board.perft(3), divide.iter().map(|(_chess_move, nodes)| nodes).sum::<u64>());
+    assert!(divide.contains(&(ChessMove::from_uci("e2e4").unwrap(), 600)));
+}
@@ -1,4 +1,4 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
 pub enum Piece {
     Pawn,
     Rook,
@@ -12,6 +12,35 @@ pub enum Piece {
 const BOARD_SIZE: usize = 8;
 
 impl Piece {
+    //Case insensitive, color is not part of the piece
+    pub fn from_char(piece_char: char) -> Option<Piece> {
+        match piece_char.to_ascii_lowercase() {
+            'p' => Some(Piece::Pawn),
+            'r' => Some(Piece::Rook),
+            'n' => Some(Piece::Knight),
+            'b' => Some(Piece::Bishop),
+            'q' => Some(Piece::Queen),
+            'k' => Some(Piece::King),
+            _ => None
+        }
+    }
+
+    pub fn to_char(&self) -> char {
+        match self {
+            Piece::Pawn => 'p',
+            Piece::Rook => 'r',
+            Piece::Knight => 'n',
+            Piece::Bishop => 'b',
+            Piece::Queen => 'q',
+            Piece::King => 'k',
+            Piece::Empty => ' '
+        }
+    }
+
+    pub fn is_promotion_piece(&self) -> bool {
+        matches!(self, Piece::Queen | Piece::Rook | Piece::Bishop | Piece::Knight)
+    }
+
     pub fn squares_that_fit_move_pattern(&self, start_x: usize, start_y: usize) -> Vec<(usize, usize)> {
         match self {
             Piece::Pawn => Piece::pawn_pattern(start_x, start_y),
@@ -71,36 +100,36 @@ impl Piece {
         let up_left_x_min_value = i32::max(0, start_x_i32 + start_y_i32 - BOARD_SIZE as i32 + 1) as usize;
         let up_left_y_max_value = i32::min(BOARD_SIZE as i32 - 1, start_x_i32 + start_y_i32) as usize;
         //While x increases, y decreases -> reverse order
-        let mut up_left: Vec::<(usize, usize)> = (up_left_x_min_value..start_x).into_iter()
-        .zip(((start_y + 1)..(up_left_y_max_value + 1)).rev().into_iter())
+        let mut up_left: Vec::<(usize, usize)> = (up_left_x_min_value..start_x)
+        .zip(((start_y + 1)..(up_left_y_max_value + 1)).rev())
         .collect();
 
         let up_right_x_max_value = i32::min(BOARD_SIZE as i32 - 1, BOARD_SIZE as i32 - 1 - start_y_i32 + start_x_i32) as usize;
         let up_right_y_max_value = i32::min(BOARD_SIZE as i32 - 1, BOARD_SIZE as i32 - 1 - start_x_i32 + start_y_i32) as usize;
         //From smaller to larger for both indices woth since both are increasing
-        let mut up_right: Vec::<(usize, usize)> = ((start_x + 1)..(up_right_x_max_value + 1)).into_iter()
-        .zip(((start_y + 1)..(up_right_y_max_value + 1)).into_iter())
+        let mut up_right: Vec::<(usize, usize)> = ((start_x + 1)..(up_right_x_max_value + 1))
+        .zip((start_y + 1)..(up_right_y_max_value + 1))
         .collect();
 
         let down_left_x_min_value = i32::max(0, start_x_i32 - start_y_i32) as usize;
         let down_left_y_min_value =  i32::max(0, start_y_i32 - start_x_i32) as usize;
         //From smaller to larger for both indices woth since both are decreasing
-        let mut down_left: Vec::<(usize, usize)> = (down_left_x_min_value..start_x).into_iter()
-        .zip((down_left_y_min_value..start_y).into_iter())
+        let mut down_left: Vec::<(usize, usize)> = (down_left_x_min_value..start_x)
+        .zip(down_left_y_min_value..start_y)
         .collect();
 
         let down_right_x_max_value = i32::min(BOARD_SIZE as i32 - 1, start_y_i32 + start_x_i32) as usize;
         let down_right_y_min_value = i32::max(0, start_y_i32 + start_x_i32 - BOARD_SIZE as i32 + 1) as usize;
         //While x decreases, y increases -> reverse order
-        let mut down_right: Vec::<(usize, usize)> = ((start_x + 1)..(down_right_x_max_value + 1)).into_iter()
-        .zip((down_right_y_min_value..start_y).rev().into_iter())
+        let mut down_right: Vec::<(usize, usize)> = ((start_x + 1)..(down_right_x_max_value + 1))
+        .zip((down_right_y_min_value..start_y).rev())
         .collect();
 
         let mut pattern: Vec::<(usize, usize)> = Vec::new();
-        pattern.append(&mut &mut up_left);
-        pattern.append(&mut &mut up_right);
-        pattern.append(&mut &mut down_left);
-        pattern.append(&mut &mut down_right);
+        pattern.append(&mut up_left);
+        pattern.append(&mut up_right);
+        pattern.append(&mut down_left);
+        pattern.append(&mut down_right);
 
 
         pattern
@@ -0,0 +1,215 @@
+use super::{board::Board, chess_move::ChessMove, color::Color, piece::Piece, square::{parse_square, square_name}};
+
+impl ChessMove {
+    //Built from the legal moves of the position so the notation only disambiguates when it has to
+    pub fn to_san(&self, board: &Board) -> String {
+        let mut san = self.san_without_suffix(board);
+        let new_board = board.make_move_with_struct(*self);
+        let opponent = new_board.player_to_move();
+        if new_board.is_check(opponent) {
+            san.push(if new_board.legal_moves().is_empty() {'#'} else {'+'});
+        }
+        san
+    }
+
+    fn san_without_suffix(&self, board: &Board) -> String {
+        let (start_x, start_y) = self.start_pos;
+        let (end_x, end_y) = self.end_pos;
+        let piece = board.tile(start_x, start_y).piece;
+        if piece == Piece::King && (end_x as i128 - start_x as i128).abs() == 2 {
+            return if end_x == 6 {"O-O".to_string()} else {"O-O-O".to_string()};
+        }
+        let capture = board.tile(end_x, end_y).piece != Piece::Empty || (piece == Piece::Pawn && start_x != end_x);
+        let mut san = String::new();
+        if piece == Piece::Pawn {
+            if capture {
+                san.push_str(&square_name(self.start_pos)[..1]);
+            }
+        } else {
+            san.push(piece.to_char().to_ascii_uppercase());
+            san.push_str(&self.disambiguation(board, piece));
+        }
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&square_name(self.end_pos));
+        if let Some(promotion) = self.promotion {
+            san.push('=');
+            san.push(promotion.to_char().to_ascii_uppercase());
+        }
+        san
+    }
+
+    fn disambiguation(&self, board: &Board, piece: Piece) -> String {
+        let rivals: Vec<(usize, usize)> = board.legal_moves().into_iter()
+        .filter(|chess_move| chess_move.end_pos == self.end_pos && chess_move.start_pos != self.start_pos)
+        .filter(|chess_move| board.tile(chess_move.start_pos.0, chess_move.start_pos.1).piece == piece)
+        .map(|chess_move| chess_move.start_pos)
+        .collect();
+        let start_square = square_name(self.start_pos);
+        if rivals.is_empty() {
+            String::new()
+        } else if rivals.iter().all(|(x, _y)| *x != self.start_pos.0) {
+            start_square[..1].to_string()
+        } else if rivals.iter().all(|(_x, y)| *y != self.start_pos.1) {
+            start_square[1..].to_string()
+        } else {
+            start_square
+        }
+    }
+}
+
+impl Board {
+    //Accepts check and annotation suffixes, a missing capture sign, a missing '=' before the promotion
+    //piece and more disambiguation than needed, but never a move that matches several legal moves
+    pub fn parse_san(&self, san: &str) -> Option<ChessMove> {
+        let san = san.trim().trim_end_matches(|c| "+#!?".contains(c));
+        let home_rank = if self.player_to_move() == Color::White {0} else {7};
+        let (piece, file, rank, end_pos, promotion) = match san {
+            "O-O" | "0-0" => (Piece::King, Some(4), Some(home_rank), (6, home_rank), None),
+            "O-O-O" | "0-0-0" => (Piece::King, Some(4), Some(home_rank), (2, home_rank), None),
+            _ => parse_san_components(san)?
+        };
+        let mut candidates = self.legal_moves().into_iter()
+        .filter(|chess_move| chess_move.end_pos == end_pos && chess_move.promotion == promotion)
+        .filter(|chess_move| self.tile(chess_move.start_pos.0, chess_move.start_pos.1).piece == piece)
+        .filter(|chess_move| file.is_none_or(|x| chess_move.start_pos.0 == x))
+        .filter(|chess_move| rank.is_none_or(|y| chess_move.start_pos.1 == y));
+        let chess_move = candidates.next()?;
+        if candidates.next().is_some() {
+            return None;
+        }
+        Some(chess_move)
+    }
+}
+
+type SanComponents = (Piece, Option<usize>, Option<usize>, (usize, usize), Option<Piece>);
+
+fn parse_san_components(san: &str) -> Option<SanComponents> {
+    let mut chars: Vec<char> = san.chars().collect();
+    let mut promotion = None;
+    if chars.last()?.is_ascii_alphabetic() {
+        let piece = Piece::from_char(chars.pop()?)?;
+        if !piece.is_promotion_piece() {
+            return None;
+        }
+        promotion = Some(piece);
+        if chars.last() == Some(&'=') {
+            chars.pop();
+        }
+    }
+    let piece = match chars.first() {
+        Some(piece_char) if "KQRBN".contains(*piece_char) => {
+            let piece = Piece::from_char(*piece_char)?;
+            chars.remove(0);
+            piece
+        },
+        _ => Piece::Pawn
+    };
+    if chars.len() < 2 || (promotion.is_some() && piece != Piece::Pawn) {
+        return None;
+    }
+    let end_pos = parse_square(&chars.split_off(chars.len() - 2).into_iter().collect::<String>())?;
+    if chars.last() == Some(&'x') {
+        chars.pop();
+    }
+    let (mut file, mut rank) = (None, None);
+    for disambiguation in chars {
+        match disambiguation {
+            'a'..='h' if file.is_none() && rank.is_none() => file = Some((disambiguation as u8 - b'a') as usize),
+            '1'..='8' if rank.is_none() => rank = Some((disambiguation as u8 - b'1') as usize),
+            _ => return None
+        }
+    }
+    Some((piece, file, rank, end_pos, promotion))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess::{board::Board, chess_move::ChessMove, piece::Piece};
+
+    fn san_moves(board: &Board) -> Vec<String> {
+        board.legal_moves().iter().map(|chess_move| chess_move.to_san(board)).collect()
+    }
+
+    #[test]
+    fn opening_moves() {
+        let board = Board::new();
+        assert_eq!("e4", ChessMove::new((4, 1), (4, 3)).to_san(&board));
+        assert_eq!("Nf3", ChessMove::new((6, 0), (5, 2)).to_san(&board));
+        assert_eq!(Some(ChessMove::new((4, 1), (4, 3))), board.parse_san("e4"));
+        assert_eq!(Some(ChessMove::new((6, 0), (5, 2))), board.parse_san("Nf3"));
+        assert_eq!(None, board.parse_san("e5"));
+        assert_eq!(None, board.parse_san("Nf4"));
+    }
+
+    #[test]
+    fn disambiguation() {
+        let board = Board::from_fen("k7/8/8/8/8/4R3/8/1N2R1K1 w - - 0 1").unwrap();
+        assert_eq!("R1e2", ChessMove::new((4, 0), (4, 1)).to_san(&board));
+        assert_eq!("R3e2", ChessMove::new((4, 2), (4, 1)).to_san(&board));
+        let board = Board::from_fen("k7/8/8/8/8/5N2/8/1N4K1 w - - 0 1").unwrap();
+        assert_eq!("Nbd2", ChessMove::new((1, 0), (3, 1)).to_san(&board));
+        assert_eq!("Nfd2", ChessMove::new((5, 2), (3, 1)).to_san(&board));
+        assert_eq!("Nc3", ChessMove::new((1, 0), (2, 2)).to_san(&board));
+        assert_eq!(None, board.parse_san("Nd2"));
+        assert_eq!(Some(ChessMove::new((1, 0), (3, 1))), board.parse_san("Nbd2"));
+        assert_eq!(Some(ChessMove::new((1, 0), (2, 2))), board.parse_san("Nb1c3"));
+        let board = Board::from_fen("8/7k/8/8/8/Q7/8/Q1Q3K1 w - - 0 1").unwrap();
+        assert_eq!("Qa1b2", ChessMove::new((0, 0), (1, 1)).to_san(&board));
+        assert_eq!(Some(ChessMove::new((0, 0), (1, 1))), board.parse_san("Qa1b2"));
+    }
+
+    #[test]
+    fn captures() {
+        let board = Board::from_fen("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2").unwrap();
+        assert_eq!("exd5", ChessMove::new((4, 3), (3, 4)).to_san(&board));
+        assert_eq!(Some(ChessMove::new((4, 3), (3, 4))), board.parse_san("exd5"));
+        assert_eq!(Some(ChessMove::new((4, 3), (3, 4))), board.parse_san("ed5"));
+        let board = Board::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap();
+        assert_eq!("exf6", ChessMove::new((4, 4), (5, 5)).to_san(&board));
+        assert_eq!(Some(ChessMove::new((4, 4), (5, 5))), board.parse_san("exf6"));
+    }
+
+    #[test]
+    fn checks_and_mates() {
+        let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 2 3").unwrap();
+        assert_eq!("Qxf7#", ChessMove::new((5, 2), (5, 6)).to_san(&board));
+        assert_eq!("Bxf7+", ChessMove::new((2, 3), (5, 6)).to_san(&board));
+        assert_eq!(Some(ChessMove::new((5, 2), (5, 6))), board.parse_san("Qxf7#"));
+        assert_eq!(Some(ChessMove::new((5, 2), (5, 6))), board.parse_san("Qxf7"));
+        assert_eq!(Some(ChessMove::new((2, 3), (5, 6))), board.parse_san("Bxf7+!?"));
+    }
+
+    #[test]
+    fn castling() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+        assert_eq!("O-O", ChessMove::new((4, 7), (6, 7)).to_san(&board));
+        assert_eq!("O-O-O", ChessMove::new((4, 7), (2, 7)).to_san(&board));
+        assert_eq!(Some(ChessMove::new((4, 7), (6, 7))), board.parse_san("O-O"));
+        assert_eq!(Some(ChessMove::new((4, 7), (2, 7))), board.parse_san("0-0-0"));
+        assert!(san_moves(&board).contains(&"O-O".to_string()));
+    }
+
+    #[test]
+    fn promotions() {
+        let board = Board::from_fen("1r5k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let queen_promotion = ChessMove{start_pos: (0, 6), end_pos: (0, 7), promotion: Some(Piece::Queen)};
+        let knight_capture = ChessMove{start_pos: (0, 6), end_pos: (1, 7), promotion: Some(Piece::Knight)};
+        assert_eq!("a8=Q", queen_promotion.to_san(&board));
+        assert_eq!("axb8=N", knight_capture.to_san(&board));
+        assert_eq!(Some(queen_promotion), board.parse_san("a8=Q+"));
+        assert_eq!(Some(queen_promotion), board.parse_san("a8Q"));
+        assert_eq!(Some(knight_capture), board.parse_san("axb8=N"));
+        assert_eq!(None, board.parse_san("a8"));
+        assert_eq!(None, board.parse_san("a8=K"));
+    }
+
+    #[test]
+    fn every_legal_move_round_trips() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        for chess_move in board.legal_moves() {
+            assert_eq!(Some(chess_move), board.parse_san(&chess_move.to_san(&board)));
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//Squares are named with file letters a-h for x and ranks 1-8 for y, so (4, 1) is "e2"
+pub fn square_name(square: (usize, usize)) -> String {
+    let (x, y) = square;
+    format!("{}{}", (b'a' + x as u8) as char, y + 1)
+}
+
+pub fn parse_square(square_string: &str) -> Option<(usize, usize)> {
+    let mut chars = square_string.chars();
+    let file = chars.next()?;
+    let rank = chars.next()?;
+    if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+        return None;
+    }
+    Some(((file as u8 - b'a') as usize, (rank as u8 - b'1') as usize))
+}
+
+#[test]
+fn name_corners() {
+    assert_eq!("a1", square_name((0, 0)));
+    assert_eq!("h8", square_name((7, 7)));
+    assert_eq!("e2", square_name((4, 1)));
+}
+
+#[test]
+fn parse_valid_squares() {
+    assert_eq!(Some((0, 0)), parse_square("a1"));
+    assert_eq!(Some((7, 7)), parse_square("h8"));
+    assert_eq!(Some((4, 3)), parse_square("e4"));
+}
+
+#[test]
+fn parse_invalid_squares() {
+    assert_eq!(None, parse_square("i1"));
+    assert_eq!(None, parse_square("a9"));
+    assert_eq!(None, parse_square("a0"));
+    assert_eq!(None, parse_square("e44"));
+    assert_eq!(None, parse_square("e"));
+    assert_eq!(None, parse_square(""));
+}
@@ -2,7 +2,7 @@ use super::color::Color;
 use super::piece::Piece;
 use std::fmt;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Hash)]
 pub struct Tile {
     pub color: Color,
     pub piece: Piece
@@ -0,0 +1,92 @@
+use super::{bitboard::{color_index, piece_index}, castling_rights::CastlingRights, color::Color, piece::Piece};
+
+//A random key for every feature of a position, a position's key is the xor of the keys of its features
+struct ZobristKeys {
+    pieces: [[[u64; 64]; 6]; 2],
+    black_to_move: u64,
+    castling: [u64; 4],
+    en_passant_file: [u64; 8]
+}
+
+//Generated at compile time with a fixed seed, so keys stay the same between runs
+static KEYS: ZobristKeys = ZobristKeys::generate();
+
+impl ZobristKeys {
+    const fn generate() -> ZobristKeys {
+        let mut state = 0x2545_F491_4F6C_DD1D;
+        let mut keys = ZobristKeys {pieces: [[[0; 64]; 6]; 2], black_to_move: 0, castling: [0; 4], en_passant_file: [0; 8]};
+        let mut color = 0;
+        while color < 2 {
+            let mut piece = 0;
+            while piece < 6 {
+                let mut square = 0;
+                while square < 64 {
+                    state = split_mix(state);
+                    keys.pieces[color][piece][square] = mix(state);
+                    square += 1;
+                }
+                piece += 1;
+            }
+            color += 1;
+        }
+        state = split_mix(state);
+        keys.black_to_move = mix(state);
+        let mut index = 0;
+        while index < 4 {
+            state = split_mix(state);
+            keys.castling[index] = mix(state);
+            index += 1;
+        }
+        index = 0;
+        while index < 8 {
+            state = split_mix(state);
+            keys.en_passant_file[index] = mix(state);
+            index += 1;
+        }
+        keys
+    }
+}
+
+//SplitMix64, a small generator that is good enough for spreading key bits
+const fn split_mix(state: u64) -> u64 {
+    state.wrapping_add(0x9E37_79B9_7F4A_7C15)
+}
+
+const fn mix(state: u64) -> u64 {
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+pub fn piece_key(color: Color, piece: Piece, square: usize) -> u64 {
+    KEYS.pieces[color_index(color)][piece_index(piece)][square]
+}
+
+pub fn black_to_move_key() -> u64 {
+    KEYS.black_to_move
+}
+
+pub fn castling_key(castling_rights: CastlingRights) -> u64 {
+    [castling_rights.white_king_side, castling_rights.white_queen_side, castling_rights.black_king_side, castling_rights.black_queen_side]
+    .iter().zip(KEYS.castling.iter())
+    .filter(|(has_right, _key)| **has_right)
+    .fold(0, |key, (_has_right, right_key)| key ^ right_key)
+}
+
+pub fn en_passant_key(file: usize) -> u64 {
+    KEYS.en_passant_file[file]
+}
+
+#[test]
+fn keys_are_distinct() {
+    let mut all_keys: Vec<u64> = KEYS.pieces.iter().flatten().flatten().copied().collect();
+    all_keys.push(KEYS.black_to_move);
+    all_keys.extend_from_slice(&KEYS.castling);
+    all_keys.extend_from_slice(&KEYS.en_passant_file);
+    let key_count = all_keys.len();
+    all_keys.sort_unstable();
+    all_keys.dedup();
+    assert_eq!(key_count, all_keys.len());
+    assert_eq!(0, castling_key(CastlingRights::none()));
+}
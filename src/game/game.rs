@@ -1,31 +1,79 @@
 use crate::chess::chess_move::ChessMove;
 use crate::chess::board::{Board};
-use crate::chess::game_result::GameResult;
+use crate::chess::color::Color;
+use crate::chess::game_result::{GameResult, Termination};
 use std::fmt;
 pub struct Game {
     moves: Vec<ChessMove>,
+    previous_boards: Vec<Board>,
     result: GameResult,
     pub board: Board
 }
 
 impl Game {
     pub fn new() -> Game {
+        Game::from_board(Board::new())
+    }
+
+    //A game continuing from any position, e.g. one set up from a FEN
+    pub fn from_board(board: Board) -> Game {
         Game {moves: Vec::<ChessMove>::new(),
-        result: GameResult::Ongoing,
-        board: Board::new()}
+        previous_boards: Vec::<Board>::new(),
+        result: board.result(),
+        board}
     }
 
     pub fn make_move(&mut self, chess_move: &ChessMove) {
-        self.moves.push(chess_move.clone());
-        self.board = self.board.make_move(
-            chess_move.start_pos.0, chess_move.start_pos.1,
-             chess_move.end_pos.0, chess_move.end_pos.1);
+        self.moves.push(*chess_move);
+        self.previous_boards.push(self.board.clone());
+        self.board = self.board.make_move_with_struct(*chess_move);
         self.result = self.board.result();
     }
 
+    //Takes back the last move, returns None if there is nothing to take back
+    pub fn undo_move(&mut self) -> Option<ChessMove> {
+        let chess_move = self.moves.pop()?;
+        self.board = self.previous_boards.pop()?;
+        self.result = self.board.result();
+        Some(chess_move)
+    }
+
     pub fn legal_move(&self, chess_move: &ChessMove) -> bool {
-        self.board.legal_move(chess_move.start_pos.0, chess_move.start_pos.1,
-            chess_move.end_pos.0, chess_move.end_pos.1)
+        self.board.legal_move_with_struct(chess_move)
+    }
+
+    pub fn moves(&self) -> &[ChessMove] {
+        &self.moves
+    }
+
+    pub fn result(&self) -> &GameResult {
+        &self.result
+    }
+
+    //Endings that don't come from the position on the board, undoing a move takes them back too
+    pub fn resign(&mut self, color: Color) {
+        self.result = win_for(Color::opposing_color(color), Termination::Resignation);
+    }
+
+    pub fn timeout(&mut self, color: Color) {
+        self.result = win_for(Color::opposing_color(color), Termination::Timeout);
+    }
+
+    pub fn agree_draw(&mut self) {
+        self.result = GameResult::Draw(Termination::Agreement);
+    }
+}
+
+fn win_for(color: Color, termination: Termination) -> GameResult {
+    match color {
+        Color::White => GameResult::WhiteWin(termination),
+        _ => GameResult::BlackWin(termination)
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
     }
 }
 
@@ -33,4 +81,42 @@ impl fmt::Display for Game {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.board)
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn undo_restores_position() {
+    let mut game = Game::new();
+    let chess_move = ChessMove::from_uci("e2e4").unwrap();
+    game.make_move(&chess_move);
+    game.make_move(&ChessMove::from_uci("e7e5").unwrap());
+    assert_eq!(2, game.moves().len());
+    game.undo_move();
+    assert_eq!(Some(chess_move), game.undo_move());
+    assert_eq!(Board::new().to_fen(), game.board.to_fen());
+    assert_eq!(None, game.undo_move());
+}
+
+#[test]
+fn undo_reopens_finished_game() {
+    let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 2 3").unwrap();
+    let mut game = Game::from_board(board);
+    game.make_move(&ChessMove::from_uci("f3f7").unwrap());
+    assert_eq!(&GameResult::WhiteWin(Termination::Checkmate), game.result());
+    game.undo_move();
+    assert_eq!(&GameResult::Ongoing, game.result());
+}
+
+#[test]
+fn resignation_timeout_and_agreement() {
+    let mut game = Game::new();
+    game.make_move(&ChessMove::from_uci("e2e4").unwrap());
+    game.resign(Color::Black);
+    assert_eq!(&GameResult::WhiteWin(Termination::Resignation), game.result());
+    game.timeout(Color::White);
+    assert_eq!("0-1", game.result().pgn_result());
+    assert_eq!("[Result \"0-1\"]\n[Termination \"timeout\"]\n", game.result().pgn_tags());
+    game.agree_draw();
+    assert_eq!(Some(Termination::Agreement), game.result().termination());
+    game.undo_move();
+    assert_eq!(&GameResult::Ongoing, game.result());
+}